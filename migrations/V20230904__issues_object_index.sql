@@ -0,0 +1 @@
+CREATE INDEX idx_issues_object_id_tech_id ON issues(object_id, issue_tech_id);
@@ -0,0 +1,13 @@
+CREATE TYPE "pricing_period" AS ENUM (
+	'hour',
+	'day',
+	'month'
+);
+
+CREATE TABLE "pricing" (
+	id UUID NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+	object_type TEXT NOT NULL UNIQUE,
+	price DOUBLE PRECISION NOT NULL,
+	period pricing_period NOT NULL,
+	description TEXT NOT NULL DEFAULT ''
+);
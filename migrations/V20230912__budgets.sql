@@ -0,0 +1,26 @@
+ALTER TYPE "issue_category" ADD VALUE 'cost' BEFORE 'unknown';
+
+CREATE TYPE "budget_scope" AS ENUM (
+	'namespace',
+	'label'
+);
+
+CREATE TABLE "budgets" (
+	id UUID NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+	name TEXT NOT NULL UNIQUE,
+	scope budget_scope NOT NULL,
+	namespace_name TEXT,
+	label_key TEXT,
+	label_value TEXT,
+	monthly_amount DOUBLE PRECISION NOT NULL,
+	thresholds DOUBLE PRECISION[] NOT NULL DEFAULT '{0.8,1.0}'
+);
+
+CREATE TABLE "budget_spendings" (
+	budget_id UUID NOT NULL,
+	month DATE NOT NULL,
+	amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+
+	CONSTRAINT pkey_budget_spendings PRIMARY KEY(budget_id, month),
+	CONSTRAINT fk_budgets FOREIGN KEY(budget_id) REFERENCES budgets(id) ON DELETE CASCADE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE "node_pricing" (
+	instance_type TEXT NOT NULL PRIMARY KEY,
+	price DOUBLE PRECISION NOT NULL,
+	period pricing_period NOT NULL,
+	description TEXT NOT NULL DEFAULT ''
+);
@@ -0,0 +1 @@
+ALTER TABLE "budgets" ADD COLUMN last_accrued_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
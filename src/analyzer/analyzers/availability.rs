@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use k8s_openapi::api::core::v1::PodSpec;
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::selectors;
+use crate::state::{self, ClusterState};
+
+const ZONE_LABEL: &str = "topology.kubernetes.io/zone";
+
+// Deployment or StatefulSet expected to run several replicas
+struct ReplicatedWorkload<'a> {
+    object: ObjectReference,
+    replicas: i32,
+    labels: Option<&'a BTreeMap<String, String>>,
+    spec: Option<&'a PodSpec>,
+}
+
+impl<'a> ReplicatedWorkload<'a> {
+    fn new(
+        kind: &str,
+        metadata: &ObjectMeta,
+        replicas: Option<i32>,
+        template_metadata: Option<&'a ObjectMeta>,
+        spec: Option<&'a PodSpec>,
+    ) -> Option<Self> {
+        Some(Self {
+            object: ObjectReference::new(
+                kind,
+                metadata.name.as_deref()?,
+                metadata.namespace.as_deref()?,
+            ),
+            replicas: replicas.unwrap_or(1),
+            labels: template_metadata.and_then(|m| m.labels.as_ref()),
+            spec,
+        })
+    }
+
+    // Whether the scheduler is told to spread replicas
+    fn is_spread(&self) -> bool {
+        let spec = match self.spec {
+            Some(s) => s,
+            None => return false,
+        };
+        let anti_affinity = spec
+            .affinity
+            .as_ref()
+            .and_then(|a| a.pod_anti_affinity.as_ref())
+            .is_some();
+        let topology_spread = spec
+            .topology_spread_constraints
+            .as_ref()
+            .map(|c| !c.is_empty())
+            .unwrap_or(false);
+        anti_affinity || topology_spread
+    }
+}
+
+fn replicated_workloads(state: &ClusterState) -> Vec<ReplicatedWorkload<'_>> {
+    let mut workloads = vec![];
+    for d in state.deployments.iter() {
+        let spec = d.spec.as_ref();
+        workloads.extend(ReplicatedWorkload::new(
+            "Deployment",
+            &d.metadata,
+            spec.and_then(|s| s.replicas),
+            spec.and_then(|s| s.template.metadata.as_ref()),
+            spec.and_then(|s| s.template.spec.as_ref()),
+        ));
+    }
+    for s in state.statefulsets.iter() {
+        let spec = s.spec.as_ref();
+        workloads.extend(ReplicatedWorkload::new(
+            "StatefulSet",
+            &s.metadata,
+            spec.and_then(|s| s.replicas),
+            spec.and_then(|s| s.template.metadata.as_ref()),
+            spec.and_then(|s| s.template.spec.as_ref()),
+        ));
+    }
+    workloads
+}
+
+fn allows_no_disruption(pdb: &PodDisruptionBudget) -> bool {
+    let spec = match pdb.spec.as_ref() {
+        Some(s) => s,
+        None => return false,
+    };
+    let zero_unavailable = match spec.max_unavailable.as_ref() {
+        Some(IntOrString::Int(i)) => *i == 0,
+        Some(IntOrString::String(s)) => s == "0%",
+        None => false,
+    };
+    // minAvailable equal to the number of pods only shows in the status, once every pod is healthy
+    let saturated = pdb
+        .status
+        .as_ref()
+        .map(|s| {
+            s.expected_pods > 0
+                && s.disruptions_allowed == 0
+                && s.current_healthy >= s.expected_pods
+        })
+        .unwrap_or(false);
+    zero_unavailable || saturated
+}
+
+// Report workloads whose availability does not survive a node drain or failure
+pub struct AvailabilityAnalyzer {
+    production_namespaces: Vec<String>,
+}
+
+impl AvailabilityAnalyzer {
+    pub fn from_env() -> Self {
+        let namespaces: String =
+            config::env_or("PRODUCTION_NAMESPACES", "prod,production".to_string());
+        Self {
+            production_namespaces: namespaces
+                .split(',')
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl Analyzer for AvailabilityAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/availability"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            state::POD_OWNER_KINDS.as_slice(),
+            &[
+                "Deployment",
+                "StatefulSet",
+                "Pod",
+                "PodDisruptionBudget",
+                "Node",
+            ],
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        let node_zones: BTreeMap<&str, &str> = state
+            .nodes
+            .iter()
+            .filter_map(|n| {
+                let zone = n.metadata.labels.as_ref()?.get(ZONE_LABEL)?;
+                Some((n.metadata.name.as_deref()?, zone.as_str()))
+            })
+            .collect();
+        let cluster_zones: BTreeSet<&str> = node_zones.values().copied().collect();
+
+        // Nodes of the running pods of every workload
+        let mut placements: BTreeMap<ObjectReference, Vec<&str>> = BTreeMap::new();
+        for pod in state.pods.iter() {
+            let phase = pod.status.as_ref().and_then(|s| s.phase.as_deref());
+            let node = pod.spec.as_ref().and_then(|s| s.node_name.as_deref());
+            if let (Some("Running"), Some(node), Some(owner)) = (phase, node, state.pod_owner(pod))
+            {
+                placements.entry(owner).or_default().push(node);
+            }
+        }
+
+        let pdbs: Vec<(&str, Option<&LabelSelector>)> = state
+            .pod_disruption_budgets
+            .iter()
+            .filter_map(|p| {
+                Some((
+                    p.metadata.namespace.as_deref()?,
+                    p.spec.as_ref().and_then(|s| s.selector.as_ref()),
+                ))
+            })
+            .collect();
+
+        for workload in replicated_workloads(state) {
+            let namespace = &workload.object.namespace;
+
+            if workload.replicas == 1
+                && workload.object.object_type == "Deployment"
+                && self.production_namespaces.contains(namespace)
+            {
+                issues.push(Issue::new(
+                    &workload.object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "single-replica-deployment",
+                    "Production deployment runs a single replica and is unavailable during restarts"
+                        .to_string(),
+                ));
+            }
+
+            if workload.replicas < 2 {
+                continue;
+            }
+
+            // PDBs without selector don't select anything
+            let protected = pdbs.iter().any(|(pdb_namespace, selector)| {
+                pdb_namespace == namespace
+                    && selector
+                        .map(|s| selectors::matches(s, workload.labels))
+                        .unwrap_or(false)
+            });
+            if !protected {
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Reliability,
+                        IssueSeverity::Medium,
+                        "missing-pod-disruption-budget",
+                        "Replicas may all be evicted at once, no PodDisruptionBudget covers them"
+                            .to_string(),
+                    )
+                    .with_details(json!({ "replicas": workload.replicas })),
+                );
+            }
+
+            if workload.is_spread() {
+                continue;
+            }
+            let nodes: BTreeSet<&str> = placements
+                .get(&workload.object)
+                .map(|n| n.iter().copied().collect())
+                .unwrap_or_default();
+            let zones: BTreeSet<&str> = nodes
+                .iter()
+                .filter_map(|n| node_zones.get(n).copied())
+                .collect();
+            let running = placements.get(&workload.object).map(Vec::len).unwrap_or(0);
+            if running < 2 {
+                continue;
+            }
+
+            if nodes.len() == 1 {
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Reliability,
+                        IssueSeverity::High,
+                        "replicas-on-single-node",
+                        "All replicas run on the same node, without anti-affinity or topology spread constraints"
+                            .to_string(),
+                    )
+                    .with_details(json!({ "nodes": nodes, "running_replicas": running })),
+                );
+            } else if zones.len() == 1 && cluster_zones.len() > 1 {
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Reliability,
+                        IssueSeverity::Medium,
+                        "replicas-on-single-zone",
+                        "All replicas run in the same zone, without anti-affinity or topology spread constraints"
+                            .to_string(),
+                    )
+                    .with_details(json!({
+                        "nodes": nodes,
+                        "zones": zones,
+                        "running_replicas": running,
+                    })),
+                );
+            }
+        }
+
+        for pdb in state.pod_disruption_budgets.iter() {
+            if !allows_no_disruption(pdb) {
+                continue;
+            }
+            let (name, namespace) = match (
+                pdb.metadata.name.as_deref(),
+                pdb.metadata.namespace.as_deref(),
+            ) {
+                (Some(n), Some(ns)) => (n, ns),
+                _ => continue,
+            };
+            let spec = pdb.spec.as_ref();
+            issues.push(
+                Issue::new(
+                    &ObjectReference::new("PodDisruptionBudget", name, namespace),
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "pdb-blocks-disruptions",
+                    "PodDisruptionBudget allows no disruption and blocks node drains".to_string(),
+                )
+                .with_details(json!({
+                    "min_available": spec.and_then(|s| s.min_available.as_ref()),
+                    "max_unavailable": spec.and_then(|s| s.max_unavailable.as_ref()),
+                })),
+            );
+        }
+
+        issues
+    }
+}
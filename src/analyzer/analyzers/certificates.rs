@@ -0,0 +1,378 @@
+use std::collections::BTreeSet;
+
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use kube::api::DynamicObject;
+use serde_json::{json, Value};
+use x509_parser::oid_registry::{
+    OID_PKCS1_MD5WITHRSAENC, OID_PKCS1_SHA1WITHRSA, OID_SIG_DSA_WITH_SHA1,
+};
+use x509_parser::pem::parse_x509_pem;
+use x509_parser::prelude::GeneralName;
+use x509_parser::public_key::PublicKey;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::ClusterState;
+
+const TLS_SECRET_TYPE: &str = "kubernetes.io/tls";
+const TLS_CERTIFICATE_KEY: &str = "tls.crt";
+
+const MIN_RSA_KEY_SIZE: usize = 2048;
+const MIN_EC_KEY_SIZE: usize = 256;
+
+// Leaf certificate of a TLS secret, as far as the analysis is concerned
+struct CertificateInfo {
+    not_after: DateTime<Utc>,
+    key_type: &'static str,
+    key_size: usize,
+    weak_signature: bool,
+    // Names the certificate is valid for, SANs or the common name without them
+    dns_names: Vec<String>,
+}
+
+impl CertificateInfo {
+    fn parse(pem: &[u8]) -> Option<Self> {
+        let (_, pem) = parse_x509_pem(pem).ok()?;
+        let certificate = pem.parse_x509().ok()?;
+
+        let (key_type, key_size) = match certificate.public_key().parsed() {
+            Ok(PublicKey::RSA(key)) => ("RSA", key.key_size()),
+            Ok(PublicKey::EC(key)) => ("EC", key.key_size()),
+            Ok(PublicKey::DSA(key)) => ("DSA", key.len() * 8),
+            _ => ("unknown", 0),
+        };
+
+        let algorithm = &certificate.signature_algorithm.algorithm;
+        let weak_signature = [
+            OID_PKCS1_MD5WITHRSAENC,
+            OID_PKCS1_SHA1WITHRSA,
+            OID_SIG_DSA_WITH_SHA1,
+        ]
+        .contains(algorithm);
+
+        let mut dns_names: Vec<String> = certificate
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .map(|san| {
+                san.value
+                    .general_names
+                    .iter()
+                    .filter_map(|n| match n {
+                        GeneralName::DNSName(name) => Some(name.to_lowercase()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if dns_names.is_empty() {
+            dns_names = certificate
+                .subject()
+                .iter_common_name()
+                .filter_map(|cn| cn.as_str().ok())
+                .map(str::to_lowercase)
+                .collect();
+        }
+
+        let not_after =
+            NaiveDateTime::from_timestamp_opt(certificate.validity().not_after.timestamp(), 0)?;
+
+        Some(Self {
+            not_after: DateTime::from_utc(not_after, Utc),
+            key_type,
+            key_size,
+            weak_signature,
+            dns_names,
+        })
+    }
+
+    fn is_weak_key(&self) -> bool {
+        match self.key_type {
+            "RSA" => self.key_size < MIN_RSA_KEY_SIZE,
+            "EC" => self.key_size < MIN_EC_KEY_SIZE,
+            "DSA" => true,
+            _ => false,
+        }
+    }
+
+    // Wildcards only cover a single label
+    fn covers(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.dns_names
+            .iter()
+            .any(|name| match name.strip_prefix("*.") {
+                Some(domain) => host
+                    .split_once('.')
+                    .map(|(label, rest)| !label.is_empty() && rest == domain)
+                    .unwrap_or(false),
+                None => *name == host,
+            })
+    }
+}
+
+fn secret_certificate(secret: &Secret) -> Option<CertificateInfo> {
+    if secret.type_.as_deref() != Some(TLS_SECRET_TYPE) {
+        return None;
+    }
+    let data = secret.data.as_ref()?.get(TLS_CERTIFICATE_KEY)?;
+    CertificateInfo::parse(&data.0)
+}
+
+// cert-manager Certificate issuing the secret, if any
+fn issuing_certificate<'a>(
+    state: &'a ClusterState,
+    secret: &ObjectReference,
+) -> Option<&'a DynamicObject> {
+    state.certificates.iter().find(|c| {
+        c.metadata.namespace.as_deref() == Some(secret.namespace.as_str())
+            && c.data.pointer("/spec/secretName").and_then(Value::as_str)
+                == Some(secret.object_name.as_str())
+    })
+}
+
+fn certificate_reference(certificate: &DynamicObject) -> Option<ObjectReference> {
+    Some(ObjectReference::new(
+        "Certificate",
+        certificate.metadata.name.as_deref()?,
+        certificate.metadata.namespace.as_deref()?,
+    ))
+}
+
+// Report expiring, expired and weak TLS certificates, and Ingresses serving hosts they don't cover
+pub struct CertificatesAnalyzer {
+    warning_window: Duration,
+    critical_window: Duration,
+}
+
+impl CertificatesAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            warning_window: Duration::days(config::env_or("CERT_EXPIRY_WARNING_DAYS", 30)),
+            critical_window: Duration::days(config::env_or("CERT_EXPIRY_CRITICAL_DAYS", 7)),
+        }
+    }
+
+    fn expiry_issue(
+        &self,
+        object: &ObjectReference,
+        certificate: &CertificateInfo,
+        now: DateTime<Utc>,
+    ) -> Option<Issue> {
+        let remaining = certificate.not_after - now;
+        let (issue_tech_id, severity, message) = if remaining <= Duration::zero() {
+            (
+                "certificate-expired",
+                IssueSeverity::Critical,
+                format!(
+                    "Certificate expired on {}",
+                    certificate.not_after.to_rfc3339()
+                ),
+            )
+        } else if remaining <= self.critical_window {
+            (
+                "certificate-expiring",
+                IssueSeverity::High,
+                format!("Certificate expires in {} days", remaining.num_days()),
+            )
+        } else if remaining <= self.warning_window {
+            (
+                "certificate-expiring",
+                IssueSeverity::Medium,
+                format!("Certificate expires in {} days", remaining.num_days()),
+            )
+        } else {
+            return None;
+        };
+
+        Some(
+            Issue::new(
+                object,
+                IssueCategory::Reliability,
+                severity,
+                issue_tech_id,
+                message,
+            )
+            .with_details(json!({
+                "not_after": certificate.not_after.to_rfc3339(),
+                "dns_names": certificate.dns_names,
+            })),
+        )
+    }
+
+    fn weakness_issues(object: &ObjectReference, certificate: &CertificateInfo) -> Vec<Issue> {
+        let mut issues = vec![];
+        if certificate.is_weak_key() {
+            issues.push(
+                Issue::new(
+                    object,
+                    IssueCategory::Security,
+                    IssueSeverity::High,
+                    "certificate-weak-key",
+                    format!(
+                        "Certificate uses a weak {} key of {} bits",
+                        certificate.key_type, certificate.key_size
+                    ),
+                )
+                .with_details(json!({
+                    "key_type": certificate.key_type,
+                    "key_size": certificate.key_size,
+                })),
+            );
+        }
+        if certificate.weak_signature {
+            issues.push(Issue::new(
+                object,
+                IssueCategory::Security,
+                IssueSeverity::Medium,
+                "certificate-weak-signature",
+                "Certificate is signed with MD5 or SHA-1".to_string(),
+            ));
+        }
+        issues
+    }
+}
+
+impl Analyzer for CertificatesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/certificates"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["Secret", "Certificate", "Ingress"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let now = Utc::now();
+        let mut issues = vec![];
+
+        for secret in state.secrets.iter() {
+            let object = match (
+                secret.metadata.name.as_deref(),
+                secret.metadata.namespace.as_deref(),
+            ) {
+                (Some(name), Some(namespace)) => ObjectReference::new("Secret", name, namespace),
+                _ => continue,
+            };
+            let certificate = match secret_certificate(secret) {
+                Some(c) => c,
+                None => continue,
+            };
+
+            // Certificates renewed by cert-manager are linked to it, as the renewal is what failed
+            let issuer = issuing_certificate(state, &object).and_then(certificate_reference);
+            if let Some(mut issue) = self.expiry_issue(&object, &certificate, now) {
+                if let Some(issuer) = issuer {
+                    issue = issue.with_linked_object(issuer);
+                }
+                issues.push(issue);
+            }
+            issues.extend(Self::weakness_issues(&object, &certificate));
+        }
+
+        for certificate in state.certificates.iter() {
+            let ready = certificate
+                .data
+                .pointer("/status/conditions")
+                .and_then(Value::as_array)
+                .and_then(|c| c.iter().find(|c| c["type"] == "Ready"));
+            let ready = match ready {
+                Some(r) if r["status"] == "False" => r,
+                _ => continue,
+            };
+            let (object, secret_name) = match (
+                certificate_reference(certificate),
+                certificate
+                    .data
+                    .pointer("/spec/secretName")
+                    .and_then(Value::as_str),
+            ) {
+                (Some(o), Some(s)) => (o, s),
+                _ => continue,
+            };
+            issues.push(
+                Issue::new(
+                    &object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "certificate-not-ready",
+                    format!(
+                        "cert-manager cannot issue the certificate: {}",
+                        ready["message"].as_str().unwrap_or_default()
+                    ),
+                )
+                .with_details(json!({
+                    "reason": ready["reason"],
+                    "not_after": certificate.data.pointer("/status/notAfter"),
+                    "renewal_time": certificate.data.pointer("/status/renewalTime"),
+                }))
+                .with_linked_object(ObjectReference::new(
+                    "Secret",
+                    secret_name,
+                    &object.namespace,
+                )),
+            );
+        }
+
+        for ingress in state.ingresses.iter() {
+            let (name, namespace) = match (
+                ingress.metadata.name.as_deref(),
+                ingress.metadata.namespace.as_deref(),
+            ) {
+                (Some(n), Some(ns)) => (n, ns),
+                _ => continue,
+            };
+            let tls = ingress.spec.as_ref().and_then(|s| s.tls.as_ref());
+            for entry in tls.iter().copied().flatten() {
+                let secret_name = match entry.secret_name.as_deref() {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let certificate = state
+                    .secrets
+                    .iter()
+                    .find(|s| {
+                        s.metadata.name.as_deref() == Some(secret_name)
+                            && s.metadata.namespace.as_deref() == Some(namespace)
+                    })
+                    .and_then(secret_certificate);
+                let certificate = match certificate {
+                    Some(c) => c,
+                    None => continue,
+                };
+
+                let uncovered: BTreeSet<&str> = entry
+                    .hosts
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .filter(|h| !certificate.covers(h))
+                    .collect();
+                if uncovered.is_empty() {
+                    continue;
+                }
+                issues.push(
+                    Issue::new(
+                        &ObjectReference::new("Ingress", name, namespace),
+                        IssueCategory::Reliability,
+                        IssueSeverity::High,
+                        "certificate-hostname-mismatch",
+                        format!(
+                            "Certificate of secret {} does not cover hosts: {}",
+                            secret_name,
+                            uncovered.iter().copied().collect::<Vec<&str>>().join(", ")
+                        ),
+                    )
+                    .with_details(json!({
+                        "hosts": uncovered,
+                        "dns_names": certificate.dns_names,
+                    }))
+                    .with_linked_object(ObjectReference::new("Secret", secret_name, namespace)),
+                );
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,719 @@
+use std::io::Read;
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use log::warn;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState};
+
+const LAST_APPLIED_ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+const HELM_RELEASE_TYPE: &str = "helm.sh/release.v1";
+const HELM_RELEASE_KEY: &str = "release";
+
+const REMOVED_API_TECH_ID: &str = "removed-api-version";
+
+type Version = (u32, u32);
+
+struct DeprecatedApi {
+    api_version: &'static str,
+    kind: &'static str,
+    deprecated_in: Version,
+    removed_in: Version,
+    replacement: &'static str,
+}
+
+const fn api(
+    api_version: &'static str,
+    kind: &'static str,
+    deprecated_in: Version,
+    removed_in: Version,
+    replacement: &'static str,
+) -> DeprecatedApi {
+    DeprecatedApi {
+        api_version,
+        kind,
+        deprecated_in,
+        removed_in,
+        replacement,
+    }
+}
+
+// From the Kubernetes deprecated API migration guide
+const DEPRECATED_APIS: [DeprecatedApi; 39] = [
+    api(
+        "extensions/v1beta1",
+        "Deployment",
+        (1, 9),
+        (1, 16),
+        "apps/v1",
+    ),
+    api(
+        "extensions/v1beta1",
+        "DaemonSet",
+        (1, 9),
+        (1, 16),
+        "apps/v1",
+    ),
+    api(
+        "extensions/v1beta1",
+        "ReplicaSet",
+        (1, 9),
+        (1, 16),
+        "apps/v1",
+    ),
+    api(
+        "extensions/v1beta1",
+        "NetworkPolicy",
+        (1, 9),
+        (1, 16),
+        "networking.k8s.io/v1",
+    ),
+    api(
+        "extensions/v1beta1",
+        "PodSecurityPolicy",
+        (1, 11),
+        (1, 16),
+        "policy/v1beta1",
+    ),
+    api(
+        "extensions/v1beta1",
+        "Ingress",
+        (1, 14),
+        (1, 22),
+        "networking.k8s.io/v1",
+    ),
+    api("apps/v1beta1", "Deployment", (1, 9), (1, 16), "apps/v1"),
+    api("apps/v1beta1", "StatefulSet", (1, 9), (1, 16), "apps/v1"),
+    api("apps/v1beta2", "Deployment", (1, 9), (1, 16), "apps/v1"),
+    api("apps/v1beta2", "StatefulSet", (1, 9), (1, 16), "apps/v1"),
+    api("apps/v1beta2", "DaemonSet", (1, 9), (1, 16), "apps/v1"),
+    api("apps/v1beta2", "ReplicaSet", (1, 9), (1, 16), "apps/v1"),
+    api(
+        "networking.k8s.io/v1beta1",
+        "Ingress",
+        (1, 19),
+        (1, 22),
+        "networking.k8s.io/v1",
+    ),
+    api(
+        "networking.k8s.io/v1beta1",
+        "IngressClass",
+        (1, 19),
+        (1, 22),
+        "networking.k8s.io/v1",
+    ),
+    api(
+        "rbac.authorization.k8s.io/v1beta1",
+        "ClusterRole",
+        (1, 17),
+        (1, 22),
+        "rbac.authorization.k8s.io/v1",
+    ),
+    api(
+        "rbac.authorization.k8s.io/v1beta1",
+        "ClusterRoleBinding",
+        (1, 17),
+        (1, 22),
+        "rbac.authorization.k8s.io/v1",
+    ),
+    api(
+        "rbac.authorization.k8s.io/v1beta1",
+        "Role",
+        (1, 17),
+        (1, 22),
+        "rbac.authorization.k8s.io/v1",
+    ),
+    api(
+        "rbac.authorization.k8s.io/v1beta1",
+        "RoleBinding",
+        (1, 17),
+        (1, 22),
+        "rbac.authorization.k8s.io/v1",
+    ),
+    api(
+        "apiextensions.k8s.io/v1beta1",
+        "CustomResourceDefinition",
+        (1, 16),
+        (1, 22),
+        "apiextensions.k8s.io/v1",
+    ),
+    api(
+        "admissionregistration.k8s.io/v1beta1",
+        "MutatingWebhookConfiguration",
+        (1, 16),
+        (1, 22),
+        "admissionregistration.k8s.io/v1",
+    ),
+    api(
+        "admissionregistration.k8s.io/v1beta1",
+        "ValidatingWebhookConfiguration",
+        (1, 16),
+        (1, 22),
+        "admissionregistration.k8s.io/v1",
+    ),
+    api(
+        "scheduling.k8s.io/v1beta1",
+        "PriorityClass",
+        (1, 14),
+        (1, 22),
+        "scheduling.k8s.io/v1",
+    ),
+    api(
+        "storage.k8s.io/v1beta1",
+        "StorageClass",
+        (1, 19),
+        (1, 22),
+        "storage.k8s.io/v1",
+    ),
+    api(
+        "storage.k8s.io/v1beta1",
+        "CSIDriver",
+        (1, 19),
+        (1, 22),
+        "storage.k8s.io/v1",
+    ),
+    api(
+        "storage.k8s.io/v1beta1",
+        "CSIStorageCapacity",
+        (1, 24),
+        (1, 27),
+        "storage.k8s.io/v1",
+    ),
+    api("batch/v1beta1", "CronJob", (1, 21), (1, 25), "batch/v1"),
+    api(
+        "policy/v1beta1",
+        "PodDisruptionBudget",
+        (1, 21),
+        (1, 25),
+        "policy/v1",
+    ),
+    api(
+        "policy/v1beta1",
+        "PodSecurityPolicy",
+        (1, 21),
+        (1, 25),
+        "Pod Security Admission",
+    ),
+    api(
+        "autoscaling/v2beta1",
+        "HorizontalPodAutoscaler",
+        (1, 22),
+        (1, 25),
+        "autoscaling/v2",
+    ),
+    api(
+        "autoscaling/v2beta2",
+        "HorizontalPodAutoscaler",
+        (1, 23),
+        (1, 26),
+        "autoscaling/v2",
+    ),
+    api(
+        "discovery.k8s.io/v1beta1",
+        "EndpointSlice",
+        (1, 21),
+        (1, 25),
+        "discovery.k8s.io/v1",
+    ),
+    api(
+        "events.k8s.io/v1beta1",
+        "Event",
+        (1, 22),
+        (1, 25),
+        "events.k8s.io/v1",
+    ),
+    api(
+        "node.k8s.io/v1beta1",
+        "RuntimeClass",
+        (1, 22),
+        (1, 25),
+        "node.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta1",
+        "FlowSchema",
+        (1, 23),
+        (1, 26),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta1",
+        "PriorityLevelConfiguration",
+        (1, 23),
+        (1, 26),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta2",
+        "FlowSchema",
+        (1, 26),
+        (1, 29),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta2",
+        "PriorityLevelConfiguration",
+        (1, 26),
+        (1, 29),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta3",
+        "FlowSchema",
+        (1, 29),
+        (1, 32),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+    api(
+        "flowcontrol.apiserver.k8s.io/v1beta3",
+        "PriorityLevelConfiguration",
+        (1, 29),
+        (1, 32),
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+];
+
+// Parse "1.27" or "v1.27.3", minor versions of managed clusters may end with a "+"
+fn parse_version(value: &str) -> Option<Version> {
+    let mut parts = value.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.trim_end_matches('+').parse().ok()?;
+    Some((major, minor))
+}
+
+fn find_deprecation(api_version: &str, kind: &str) -> Option<&'static DeprecatedApi> {
+    DEPRECATED_APIS
+        .iter()
+        .find(|a| a.api_version == api_version && a.kind == kind)
+}
+
+// Manifest of an object, as found in a last applied configuration or an Helm release
+struct Manifest {
+    api_version: String,
+    kind: String,
+    name: String,
+}
+
+impl Manifest {
+    // Items of a List are manifests of their own
+    fn collect(object: &Value, manifests: &mut Vec<Manifest>) {
+        if object["kind"] == "List" {
+            for item in object["items"].as_array().iter().copied().flatten() {
+                Self::collect(item, manifests);
+            }
+            return;
+        }
+        if let (Some(api_version), Some(kind)) =
+            (object["apiVersion"].as_str(), object["kind"].as_str())
+        {
+            manifests.push(Manifest {
+                api_version: api_version.to_string(),
+                kind: kind.to_string(),
+                name: object["metadata"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+    }
+}
+
+// Objects of every document of a YAML stream, documents which can't be parsed are skipped
+fn yaml_manifests(manifest: &str) -> Vec<Manifest> {
+    let mut manifests = vec![];
+    for document in serde_yaml::Deserializer::from_str(manifest) {
+        if let Ok(object) = Value::deserialize(document) {
+            Manifest::collect(&object, &mut manifests);
+        }
+    }
+    manifests
+}
+
+// Helm stores releases gzipped and base64 encoded, on top of the secret own encoding
+fn helm_release(secret: &Secret) -> Option<Value> {
+    let data = secret.data.as_ref()?.get(HELM_RELEASE_KEY)?;
+    let compressed = base64::engine::general_purpose::STANDARD
+        .decode(&data.0)
+        .ok()?;
+    let mut release = String::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut release)
+        .ok()?;
+    serde_json::from_str(&release).ok()
+}
+
+fn is_deployed_release(secret: &Secret) -> bool {
+    secret.type_.as_deref() == Some(HELM_RELEASE_TYPE)
+        && secret
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|l| l.get("status"))
+            .map(String::as_str)
+            == Some("deployed")
+}
+
+// Report objects using API versions deprecated or removed by the target Kubernetes version
+pub struct DeprecatedApisAnalyzer {
+    // Next minor version of the cluster when unset
+    target_version: Option<Version>,
+}
+
+impl DeprecatedApisAnalyzer {
+    pub fn from_env() -> Self {
+        let target: String = config::env_or("TARGET_KUBERNETES_VERSION", String::new());
+        let target_version = parse_version(&target);
+        if target_version.is_none() && !target.trim().is_empty() {
+            warn!(
+                "Invalid TARGET_KUBERNETES_VERSION {}, targeting the next minor version of the cluster",
+                target
+            );
+        }
+        Self { target_version }
+    }
+
+    fn issue(
+        &self,
+        object: &ObjectReference,
+        deprecation: &DeprecatedApi,
+        current: Option<Version>,
+        target: Version,
+        source: &str,
+    ) -> Option<Issue> {
+        let (issue_tech_id, severity, message) = if current
+            .map(|c| deprecation.removed_in <= c)
+            .unwrap_or(false)
+        {
+            (
+                REMOVED_API_TECH_ID,
+                IssueSeverity::Critical,
+                format!(
+                    "{} {} was removed in Kubernetes {}.{} and can't be applied anymore",
+                    deprecation.api_version,
+                    deprecation.kind,
+                    deprecation.removed_in.0,
+                    deprecation.removed_in.1
+                ),
+            )
+        } else if deprecation.removed_in <= target {
+            (
+                REMOVED_API_TECH_ID,
+                IssueSeverity::High,
+                format!(
+                    "{} {} is removed in Kubernetes {}.{}",
+                    deprecation.api_version,
+                    deprecation.kind,
+                    deprecation.removed_in.0,
+                    deprecation.removed_in.1
+                ),
+            )
+        } else if deprecation.deprecated_in <= target {
+            (
+                "deprecated-api-version",
+                IssueSeverity::Low,
+                format!(
+                    "{} {} is deprecated since Kubernetes {}.{}",
+                    deprecation.api_version,
+                    deprecation.kind,
+                    deprecation.deprecated_in.0,
+                    deprecation.deprecated_in.1
+                ),
+            )
+        } else {
+            return None;
+        };
+
+        Some(
+            Issue::new(
+                object,
+                IssueCategory::Configuration,
+                severity,
+                issue_tech_id,
+                message,
+            )
+            .with_details(json!({
+                "api_version": deprecation.api_version,
+                "kind": deprecation.kind,
+                "replacement": deprecation.replacement,
+                "removed_in": format!("{}.{}", deprecation.removed_in.0, deprecation.removed_in.1),
+                "target_version": format!("{}.{}", target.0, target.1),
+                "source": source,
+            })),
+        )
+    }
+}
+
+// Kinds metadata_objects reads
+const METADATA_KINDS: [&str; 22] = [
+    "Deployment",
+    "StatefulSet",
+    "DaemonSet",
+    "CronJob",
+    "Job",
+    "Service",
+    "Ingress",
+    "NetworkPolicy",
+    "PodDisruptionBudget",
+    "HorizontalPodAutoscaler",
+    "ConfigMap",
+    "ServiceAccount",
+    "Role",
+    "ClusterRole",
+    "RoleBinding",
+    "ClusterRoleBinding",
+    "StorageClass",
+    "CSIDriver",
+    "PriorityClass",
+    "CustomResourceDefinition",
+    "MutatingWebhookConfiguration",
+    "ValidatingWebhookConfiguration",
+];
+
+fn metadata_objects(state: &ClusterState) -> Vec<&ObjectMeta> {
+    fn add<'a, K: kube::Resource>(objects: &mut Vec<&'a ObjectMeta>, list: &'a [K]) {
+        objects.extend(list.iter().map(|o| o.meta()));
+    }
+
+    let mut objects = vec![];
+    add(&mut objects, &state.deployments);
+    add(&mut objects, &state.statefulsets);
+    add(&mut objects, &state.daemonsets);
+    add(&mut objects, &state.cronjobs);
+    add(&mut objects, &state.jobs);
+    add(&mut objects, &state.services);
+    add(&mut objects, &state.ingresses);
+    add(&mut objects, &state.network_policies);
+    add(&mut objects, &state.pod_disruption_budgets);
+    add(&mut objects, &state.horizontal_pod_autoscalers);
+    add(&mut objects, &state.configmaps);
+    add(&mut objects, &state.service_accounts);
+    add(&mut objects, &state.roles);
+    add(&mut objects, &state.cluster_roles);
+    add(&mut objects, &state.role_bindings);
+    add(&mut objects, &state.cluster_role_bindings);
+    add(&mut objects, &state.storage_classes);
+    add(&mut objects, &state.csi_drivers);
+    add(&mut objects, &state.priority_classes);
+    add(&mut objects, &state.custom_resource_definitions);
+    add(&mut objects, &state.mutating_webhook_configurations);
+    add(&mut objects, &state.validating_webhook_configurations);
+    objects
+}
+
+impl Analyzer for DeprecatedApisAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/deprecated-apis"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            &[state::SERVER_VERSION, "Secret"],
+            METADATA_KINDS.as_slice(),
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let current = parse_version(&format!(
+            "{}.{}",
+            state.server_version.major, state.server_version.minor
+        ));
+        let target = match self
+            .target_version
+            .or(current.map(|(major, minor)| (major, minor + 1)))
+        {
+            Some(t) => t,
+            None => return vec![],
+        };
+        let mut issues = vec![];
+
+        for metadata in metadata_objects(state) {
+            let applied: Value = match metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(LAST_APPLIED_ANNOTATION))
+                .and_then(|a| serde_json::from_str(a).ok())
+            {
+                Some(a) => a,
+                None => continue,
+            };
+            let (api_version, kind, name) = match (
+                applied["apiVersion"].as_str(),
+                applied["kind"].as_str(),
+                metadata.name.as_deref(),
+            ) {
+                (Some(v), Some(k), Some(n)) => (v, k, n),
+                _ => continue,
+            };
+            if let Some(deprecation) = find_deprecation(api_version, kind) {
+                let object = ObjectReference::new(
+                    kind,
+                    name,
+                    metadata.namespace.as_deref().unwrap_or_default(),
+                );
+                issues.extend(self.issue(
+                    &object,
+                    deprecation,
+                    current,
+                    target,
+                    "last-applied-configuration",
+                ));
+            }
+        }
+
+        for secret in state.secrets.iter().filter(|s| is_deployed_release(s)) {
+            let (name, namespace, release) = match (
+                secret.metadata.name.as_deref(),
+                secret.metadata.namespace.as_deref(),
+                helm_release(secret),
+            ) {
+                (Some(n), Some(ns), Some(r)) => (n, ns, r),
+                _ => continue,
+            };
+            let release_name = release["name"].as_str().unwrap_or_default();
+            let manifests = yaml_manifests(release["manifest"].as_str().unwrap_or_default());
+            let object = ObjectReference::new("Secret", name, namespace);
+            let source = format!("helm release {}", release_name);
+
+            // One issue per release and tech id, listing the rendered objects concerned
+            let mut release_issues: Vec<(Issue, Vec<Value>)> = vec![];
+            for manifest in manifests {
+                let deprecation = match find_deprecation(&manifest.api_version, &manifest.kind) {
+                    Some(d) => d,
+                    None => continue,
+                };
+                let issue = match self.issue(&object, deprecation, current, target, &source) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                let resource = json!({
+                    "api_version": manifest.api_version,
+                    "kind": manifest.kind,
+                    "name": manifest.name,
+                    "replacement": deprecation.replacement,
+                });
+                match release_issues
+                    .iter_mut()
+                    .find(|(i, _)| i.issue_tech_id == issue.issue_tech_id)
+                {
+                    Some((existing, resources)) => {
+                        if issue.severity > existing.severity {
+                            existing.severity = issue.severity;
+                        }
+                        resources.push(resource);
+                    }
+                    None => release_issues.push((issue, vec![resource])),
+                }
+            }
+
+            for (mut issue, resources) in release_issues {
+                let kinds: Vec<&str> = resources
+                    .iter()
+                    .filter_map(|r| r["kind"].as_str())
+                    .collect();
+                issue.issue_message = format!(
+                    "Helm release {} renders objects with {} API versions: {}",
+                    release_name,
+                    if issue.issue_tech_id == REMOVED_API_TECH_ID {
+                        "removed"
+                    } else {
+                        "deprecated"
+                    },
+                    kinds.join(", ")
+                );
+                issues.push(issue.with_details(json!({
+                    "release": release_name,
+                    "resources": resources,
+                    "target_version": format!("{}.{}", target.0, target.1),
+                })));
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("1.27"), Some((1, 27)));
+        assert_eq!(parse_version("v1.27.3"), Some((1, 27)));
+        assert_eq!(parse_version(" 1.26+ "), Some((1, 26)));
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("1"), None);
+        assert_eq!(parse_version("latest"), None);
+        assert_eq!(parse_version("1.x"), None);
+    }
+
+    #[test]
+    fn reads_every_document() {
+        let manifests = yaml_manifests(
+            "---
+# Source: chart/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  labels:
+    name: not-the-name
+---
+apiVersion: \"policy/v1beta1\"
+kind: PodDisruptionBudget
+metadata: {name: web}
+---
+",
+        );
+        let found: Vec<(&str, &str, &str)> = manifests
+            .iter()
+            .map(|m| (m.api_version.as_str(), m.kind.as_str(), m.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("apps/v1", "Deployment", "web"),
+                ("policy/v1beta1", "PodDisruptionBudget", "web"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_list_items() {
+        let manifests = yaml_manifests(
+            "apiVersion: v1
+kind: List
+items:
+- apiVersion: batch/v1beta1
+  kind: CronJob
+  metadata:
+    name: backup
+- apiVersion: autoscaling/v2beta2
+  kind: HorizontalPodAutoscaler
+  metadata:
+    name: web
+",
+        );
+        let kinds: Vec<&str> = manifests.iter().map(|m| m.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["CronJob", "HorizontalPodAutoscaler"]);
+        assert_eq!(manifests[0].api_version, "batch/v1beta1");
+    }
+
+    #[test]
+    fn skips_invalid_documents() {
+        assert!(yaml_manifests("").is_empty());
+        assert!(yaml_manifests("kind: Deployment\nmetadata:\n  name: web\n").is_empty());
+    }
+}
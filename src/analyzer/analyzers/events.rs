@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::events::{EventStore, WarningEvent};
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState};
+
+pub(super) struct Rule {
+    pub(super) reason: &'static str,
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: &'static str,
+}
+
+// Reasons are the only ones the event store keeps
+pub(super) const RULES: [Rule; 6] = [
+    Rule {
+        reason: "FailedScheduling",
+        issue_tech_id: "recurring-failed-scheduling",
+        severity: IssueSeverity::High,
+        message: "Pods repeatedly fail to be scheduled",
+    },
+    Rule {
+        reason: "FailedMount",
+        issue_tech_id: "recurring-failed-mount",
+        severity: IssueSeverity::High,
+        message: "Volumes repeatedly fail to be mounted",
+    },
+    Rule {
+        reason: "BackOff",
+        issue_tech_id: "recurring-backoff",
+        severity: IssueSeverity::Medium,
+        message: "Containers repeatedly back off restarting",
+    },
+    Rule {
+        reason: "Unhealthy",
+        issue_tech_id: "recurring-unhealthy",
+        severity: IssueSeverity::Medium,
+        message: "Container probes repeatedly fail",
+    },
+    Rule {
+        reason: "FailedCreate",
+        issue_tech_id: "recurring-failed-create",
+        severity: IssueSeverity::High,
+        message: "Controller repeatedly fails to create pods",
+    },
+    Rule {
+        reason: "Evicted",
+        issue_tech_id: "recurring-eviction",
+        severity: IssueSeverity::Medium,
+        message: "Pods are repeatedly evicted",
+    },
+];
+
+// Occurrences of a warning reason on an object, during the analysis window
+struct Occurrences<'a> {
+    count: i32,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    last_message: &'a str,
+}
+
+impl<'a> Occurrences<'a> {
+    fn new(event: &'a WarningEvent) -> Self {
+        let mut occurrences = Self {
+            count: 0,
+            first_seen: DateTime::<Utc>::MAX_UTC,
+            last_seen: DateTime::<Utc>::MIN_UTC,
+            last_message: &event.message,
+        };
+        occurrences.add(event);
+        occurrences
+    }
+
+    // Store events only keep their occurrences within the window
+    fn add(&mut self, event: &'a WarningEvent) {
+        for (seen, count) in event.occurrences.iter() {
+            self.count += count;
+            self.first_seen = self.first_seen.min(*seen);
+            if *seen >= self.last_seen {
+                self.last_seen = *seen;
+                self.last_message = &event.message;
+            }
+        }
+    }
+}
+
+// Report warning events which keep happening, aggregated by involved object and reason
+pub struct EventsAnalyzer {
+    store: EventStore,
+    window: Duration,
+    min_occurrences: i32,
+}
+
+impl EventsAnalyzer {
+    pub fn from_env(store: EventStore) -> Self {
+        Self {
+            store,
+            window: Duration::seconds(config::env_or("EVENTS_WINDOW", 3600)),
+            min_occurrences: config::env_or("EVENTS_MIN_OCCURRENCES", 3),
+        }
+    }
+}
+
+// Pods come and go, their events are reported on the workload owning them
+fn owner(state: &ClusterState, involved: &ObjectReference) -> ObjectReference {
+    if involved.object_type != "Pod" {
+        return involved.clone();
+    }
+    state
+        .pods
+        .iter()
+        .find(|p| {
+            p.metadata.name.as_deref() == Some(involved.object_name.as_str())
+                && p.metadata.namespace.as_deref() == Some(involved.namespace.as_str())
+        })
+        .and_then(|p| state.pod_owner(p))
+        .unwrap_or_else(|| involved.clone())
+}
+
+impl Analyzer for EventsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/events"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [state::POD_OWNER_KINDS.as_slice(), &["Pod"]].concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let events = self.store.recent(Utc::now() - self.window);
+
+        let mut occurrences: BTreeMap<(&ObjectReference, &str), Occurrences> = BTreeMap::new();
+        for event in events.iter() {
+            occurrences
+                .entry((&event.involved_object, event.reason.as_str()))
+                .and_modify(|o| o.add(event))
+                .or_insert_with(|| Occurrences::new(event));
+        }
+
+        let mut issues = vec![];
+        for ((involved, reason), occurrences) in occurrences {
+            let rule = match RULES.iter().find(|r| r.reason == reason) {
+                Some(r) => r,
+                None => continue,
+            };
+            if occurrences.count < self.min_occurrences {
+                continue;
+            }
+
+            let object = owner(state, involved);
+            let mut issue = Issue::new(
+                &object,
+                IssueCategory::Reliability,
+                rule.severity,
+                rule.issue_tech_id,
+                format!(
+                    "{} ({} times in the last {} minutes)",
+                    rule.message,
+                    occurrences.count,
+                    self.window.num_minutes()
+                ),
+            )
+            .with_details(json!({
+                "reason": reason,
+                "involved_object": involved,
+                "occurrences": occurrences.count,
+                "first_seen": occurrences.first_seen.to_rfc3339(),
+                "last_seen": occurrences.last_seen.to_rfc3339(),
+                "window_seconds": self.window.num_seconds(),
+                "last_message": occurrences.last_message,
+            }));
+            if &object != involved {
+                issue = issue.with_linked_object(involved.clone());
+            }
+            issues.push(issue);
+        }
+
+        issues
+    }
+}
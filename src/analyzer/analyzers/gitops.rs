@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use coi::gitops::{GitOpsApplication, GitOpsTool, HEALTH_DEGRADED, SYNC_OUT_OF_SYNC};
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState};
+
+const OUT_OF_SYNC_TECH_ID: &str = "gitops-out-of-sync";
+const DEGRADED_TECH_ID: &str = "gitops-degraded";
+const RECONCILIATION_FAILED_TECH_ID: &str = "gitops-reconciliation-failed";
+
+const WORKLOAD_KINDS: [&str; 5] = ["Deployment", "StatefulSet", "DaemonSet", "Job", "CronJob"];
+
+struct Problem {
+    issue_tech_id: &'static str,
+    category: IssueCategory,
+    severity: IssueSeverity,
+    message: String,
+    // Since when the problem is known by the GitOps tool itself
+    reported_since: Option<DateTime<Utc>>,
+}
+
+// Report applications out of sync or failing for longer than a threshold
+pub struct GitOpsAnalyzer {
+    threshold: Duration,
+    // First time each problem was observed, for tools not tracking status transitions
+    first_seen: HashMap<(ObjectReference, &'static str), DateTime<Utc>>,
+}
+
+impl GitOpsAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            threshold: Duration::seconds(config::env_or("GITOPS_PROBLEM_THRESHOLD", 900)),
+            first_seen: HashMap::new(),
+        }
+    }
+}
+
+fn problems(app: &GitOpsApplication) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    if app.sync_status.as_deref() == Some(SYNC_OUT_OF_SYNC) {
+        problems.push(Problem {
+            issue_tech_id: OUT_OF_SYNC_TECH_ID,
+            category: IssueCategory::Configuration,
+            severity: IssueSeverity::Medium,
+            message: format!(
+                "{} {} is out of sync with its source revision",
+                app.kind, app.name
+            ),
+            reported_since: None,
+        });
+    }
+
+    if app.health_status.as_deref() == Some(HEALTH_DEGRADED) {
+        let (issue_tech_id, message) = match app.tool {
+            GitOpsTool::ArgoCD => (
+                DEGRADED_TECH_ID,
+                format!("{} {} is degraded", app.kind, app.name),
+            ),
+            GitOpsTool::Flux => (
+                RECONCILIATION_FAILED_TECH_ID,
+                format!("{} {} is failing to reconcile", app.kind, app.name),
+            ),
+        };
+        problems.push(Problem {
+            issue_tech_id,
+            category: IssueCategory::Reliability,
+            severity: IssueSeverity::High,
+            message,
+            reported_since: app
+                .status_since
+                .as_deref()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|s| s.with_timezone(&Utc)),
+        });
+    }
+
+    problems
+}
+
+// Workloads deployed by the application, issues are linked to them
+fn managed_workloads(app: &GitOpsApplication) -> Vec<ObjectReference> {
+    app.resources
+        .iter()
+        .filter(|r| WORKLOAD_KINDS.contains(&r.kind.as_str()))
+        .map(|r| {
+            let namespace = r
+                .namespace
+                .as_ref()
+                .or(app.destination_namespace.as_ref())
+                .unwrap_or(&app.namespace);
+            ObjectReference::new(&r.kind, &r.name, namespace)
+        })
+        .collect()
+}
+
+impl Analyzer for GitOpsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/gitops"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec![state::GITOPS_APPLICATIONS]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let now = Utc::now();
+        let mut issues = vec![];
+        let mut first_seen = HashMap::new();
+
+        for app in state.gitops_applications.iter() {
+            let object = ObjectReference::new(&app.kind, &app.name, &app.namespace);
+
+            for problem in problems(app) {
+                let key = (object.clone(), problem.issue_tech_id);
+                let seen = *self.first_seen.get(&key).unwrap_or(&now);
+                first_seen.insert(key, seen);
+
+                let since = problem.reported_since.unwrap_or(seen);
+                if now - since < self.threshold {
+                    continue;
+                }
+
+                let issue = Issue::new(
+                    &object,
+                    problem.category,
+                    problem.severity,
+                    problem.issue_tech_id,
+                    problem.message,
+                )
+                .with_details(json!({
+                    "tool": app.tool,
+                    "sync_status": app.sync_status,
+                    "health_status": app.health_status,
+                    "revision": app.revision,
+                    "target_revision": app.target_revision,
+                    "status_message": app.status_message,
+                    "since": since.to_rfc3339(),
+                }));
+
+                let workloads = managed_workloads(app);
+                if workloads.is_empty() {
+                    issues.push(issue);
+                    continue;
+                }
+                for workload in workloads {
+                    issues.push(issue.clone().with_linked_object(workload));
+                }
+            }
+        }
+
+        // Resolved problems are forgotten
+        self.first_seen = first_seen;
+        issues
+    }
+}
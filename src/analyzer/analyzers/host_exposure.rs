@@ -0,0 +1,147 @@
+use serde_json::json;
+
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::state::{self, ClusterState, Workload};
+
+// Host paths granting control over the node, or its container runtime
+const SENSITIVE_HOST_PATHS: [&str; 11] = [
+    "/",
+    "/etc",
+    "/root",
+    "/proc",
+    "/boot",
+    "/var/run/docker.sock",
+    "/run/docker.sock",
+    "/var/run/containerd/containerd.sock",
+    "/run/containerd/containerd.sock",
+    "/var/run/crio/crio.sock",
+    "/var/lib/kubelet",
+];
+
+fn is_sensitive_host_path(path: &str) -> bool {
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        p => p,
+    };
+    SENSITIVE_HOST_PATHS.iter().any(|sensitive| {
+        path == *sensitive || (*sensitive != "/" && path.starts_with(&format!("{}/", sensitive)))
+    })
+}
+
+// Report workloads sharing host namespaces, ports or filesystem
+pub struct HostExposureAnalyzer {}
+
+impl HostExposureAnalyzer {
+    fn host_namespaces_issues(workload: &Workload) -> Vec<Issue> {
+        let spec = workload.spec;
+        let namespaces = [
+            ("host-network", spec.host_network, "network"),
+            ("host-pid", spec.host_pid, "PID"),
+            ("host-ipc", spec.host_ipc, "IPC"),
+        ];
+
+        namespaces
+            .iter()
+            .filter(|(_, enabled, _)| enabled.unwrap_or(false))
+            .map(|(issue_tech_id, _, namespace)| {
+                Issue::new(
+                    &workload.object,
+                    IssueCategory::Security,
+                    IssueSeverity::High,
+                    issue_tech_id,
+                    format!("Pods share the host {} namespace", namespace),
+                )
+            })
+            .collect()
+    }
+
+    fn host_ports_issue(workload: &Workload) -> Option<Issue> {
+        let ports: Vec<_> = workload
+            .containers()
+            .flat_map(|c| c.ports.iter().flatten().map(move |p| (c, p)))
+            .filter_map(|(c, p)| {
+                p.host_port
+                    .map(|host_port| json!({ "container": c.name, "host_port": host_port }))
+            })
+            .collect();
+        if ports.is_empty() {
+            return None;
+        }
+
+        Some(
+            Issue::new(
+                &workload.object,
+                IssueCategory::Security,
+                IssueSeverity::Medium,
+                "host-port",
+                format!("Containers bind {} host ports", ports.len()),
+            )
+            .with_details(json!({ "ports": ports })),
+        )
+    }
+
+    fn host_paths_issue(workload: &Workload) -> Option<Issue> {
+        let paths: Vec<&str> = workload
+            .spec
+            .volumes
+            .iter()
+            .flatten()
+            .filter_map(|v| v.host_path.as_ref())
+            .map(|h| h.path.as_str())
+            .collect();
+        if paths.is_empty() {
+            return None;
+        }
+
+        let sensitive: Vec<&str> = paths
+            .iter()
+            .copied()
+            .filter(|p| is_sensitive_host_path(p))
+            .collect();
+        let (severity, message) = if sensitive.is_empty() {
+            (
+                IssueSeverity::Medium,
+                format!("Pods mount host paths: {}", paths.join(", ")),
+            )
+        } else {
+            (
+                IssueSeverity::Critical,
+                format!("Pods mount sensitive host paths: {}", sensitive.join(", ")),
+            )
+        };
+
+        Some(
+            Issue::new(
+                &workload.object,
+                IssueCategory::Security,
+                severity,
+                "host-path-volume",
+                message,
+            )
+            .with_details(json!({ "paths": paths, "sensitive_paths": sensitive })),
+        )
+    }
+}
+
+impl Analyzer for HostExposureAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/host-exposure"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        state::WORKLOAD_KINDS.to_vec()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        for workload in state.workloads() {
+            issues.extend(Self::host_namespaces_issues(&workload));
+            issues.extend(Self::host_ports_issue(&workload));
+            issues.extend(Self::host_paths_issue(&workload));
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,234 @@
+use k8s_openapi::api::core::v1::Container;
+use log::warn;
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::state::{self, ClusterState, Workload};
+
+const DEFAULT_REGISTRY: &str = "docker.io";
+// Other hosts serving Docker Hub images
+const DEFAULT_REGISTRY_ALIASES: [&str; 2] = ["index.docker.io", "registry-1.docker.io"];
+
+// Host names are case insensitive and Docker Hub is reachable under several of them
+fn canonical_registry(registry: &str) -> String {
+    let registry = registry.to_ascii_lowercase();
+    if DEFAULT_REGISTRY_ALIASES.contains(&registry.as_str()) {
+        DEFAULT_REGISTRY.to_string()
+    } else {
+        registry
+    }
+}
+
+// Parts of an image reference, [registry/]repository[:tag][@digest]
+struct ImageReference<'a> {
+    registry: &'a str,
+    tag: Option<&'a str>,
+    digest: Option<&'a str>,
+}
+
+impl<'a> ImageReference<'a> {
+    fn parse(image: &'a str) -> Self {
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (image, None),
+        };
+
+        // The first component is a registry only when it looks like a host
+        let registry = match name.split_once('/') {
+            Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => {
+                host
+            }
+            _ => DEFAULT_REGISTRY,
+        };
+
+        let last_component = name.rsplit('/').next().unwrap_or(name);
+        let tag = last_component.split_once(':').map(|(_, tag)| tag);
+
+        Self {
+            registry,
+            tag,
+            digest,
+        }
+    }
+}
+
+struct Rule {
+    issue_tech_id: &'static str,
+    category: IssueCategory,
+    severity: IssueSeverity,
+    message: &'static str,
+}
+
+const MUTABLE_TAG: Rule = Rule {
+    issue_tech_id: "image-latest-tag",
+    category: IssueCategory::Configuration,
+    severity: IssueSeverity::Medium,
+    message: "Containers use the latest tag or no tag, the deployed version is unknown",
+};
+const NOT_PINNED: Rule = Rule {
+    issue_tech_id: "image-not-pinned",
+    category: IssueCategory::Security,
+    severity: IssueSeverity::Low,
+    message: "Container images are not pinned by digest and may change under the same tag",
+};
+const ALWAYS_PULL_PINNED: Rule = Rule {
+    issue_tech_id: "image-pull-always-pinned",
+    category: IssueCategory::Configuration,
+    severity: IssueSeverity::Low,
+    message: "Containers pull images pinned by digest on every start",
+};
+const UNTRUSTED_REGISTRY: Rule = Rule {
+    issue_tech_id: "image-untrusted-registry",
+    category: IssueCategory::Security,
+    severity: IssueSeverity::High,
+    message: "Containers use images from registries outside of the allowed ones",
+};
+
+// Report container images which are mutable, unpinned or from untrusted registries
+pub struct ImagesAnalyzer {
+    // Registry hosts, with an optional port, as in ghcr.io or registry.local:5000.
+    // Entries are matched against the whole host, a path prefix such as ghcr.io/org
+    // is not supported. Registry check is disabled when empty
+    allowed_registries: Vec<String>,
+}
+
+impl ImagesAnalyzer {
+    pub fn from_env() -> Self {
+        let registries: String = config::env_or("ALLOWED_REGISTRIES", String::new());
+        let mut allowed_registries = vec![];
+        for registry in registries.split(',').map(str::trim) {
+            if registry.is_empty() {
+                continue;
+            }
+            if registry.contains('/') {
+                warn!(
+                    "Ignoring allowed registry {}, expecting a host without path",
+                    registry
+                );
+                continue;
+            }
+            allowed_registries.push(canonical_registry(registry));
+        }
+        Self { allowed_registries }
+    }
+
+    fn violated_rules(&self, container: &Container) -> Vec<&'static Rule> {
+        let image = match container.image.as_deref() {
+            Some(i) => ImageReference::parse(i),
+            None => return vec![],
+        };
+        let mut rules = vec![];
+
+        if image.digest.is_none() {
+            if matches!(image.tag, None | Some("latest")) {
+                rules.push(&MUTABLE_TAG);
+            }
+            rules.push(&NOT_PINNED);
+        } else if container.image_pull_policy.as_deref() == Some("Always") {
+            rules.push(&ALWAYS_PULL_PINNED);
+        }
+
+        if !self.allowed_registries.is_empty()
+            && !self
+                .allowed_registries
+                .contains(&canonical_registry(image.registry))
+        {
+            rules.push(&UNTRUSTED_REGISTRY);
+        }
+
+        rules
+    }
+
+    fn workload_issues(&self, workload: &Workload) -> Vec<Issue> {
+        let mut findings: Vec<(&Rule, Vec<&Container>)> = vec![];
+
+        for container in workload.containers() {
+            for rule in self.violated_rules(container) {
+                match findings
+                    .iter_mut()
+                    .find(|(r, _)| r.issue_tech_id == rule.issue_tech_id)
+                {
+                    Some((_, containers)) => containers.push(container),
+                    None => findings.push((rule, vec![container])),
+                }
+            }
+        }
+
+        findings
+            .into_iter()
+            .map(|(rule, containers)| {
+                let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+                let images: Vec<_> = containers
+                    .iter()
+                    .map(|c| json!({ "container": c.name, "image": c.image }))
+                    .collect();
+                Issue::new(
+                    &workload.object,
+                    rule.category,
+                    rule.severity,
+                    rule.issue_tech_id,
+                    format!("{}: {}", rule.message, names.join(", ")),
+                )
+                .with_details(json!({
+                    "images": images,
+                    "allowed_registries": self.allowed_registries,
+                }))
+            })
+            .collect()
+    }
+}
+
+impl Analyzer for ImagesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/images"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        state::WORKLOAD_KINDS.to_vec()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        state
+            .workloads()
+            .iter()
+            .flat_map(|w| self.workload_issues(w))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(image: &str) -> Container {
+        Container {
+            name: "app".to_string(),
+            image: Some(image.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_docker_hub_aliases() {
+        let analyzer = ImagesAnalyzer {
+            allowed_registries: vec![canonical_registry("index.docker.io")],
+        };
+        for image in [
+            "nginx@sha256:0123",
+            "docker.io/library/nginx@sha256:0123",
+            "registry-1.docker.io/library/nginx@sha256:0123",
+            "Index.Docker.io/library/nginx@sha256:0123",
+        ] {
+            assert!(
+                analyzer.violated_rules(&container(image)).is_empty(),
+                "{}",
+                image
+            );
+        }
+        let rules = analyzer.violated_rules(&container("ghcr.io/org/app@sha256:0123"));
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].issue_tech_id, UNTRUSTED_REGISTRY.issue_tech_id);
+    }
+}
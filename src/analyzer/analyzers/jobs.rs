@@ -0,0 +1,382 @@
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState};
+
+// Tolerated delay over the schedule period before a CronJob is considered not scheduling
+const SCHEDULE_TOLERANCE: i32 = 2;
+
+fn job_condition(job: &Job, condition: &str) -> bool {
+    job.status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .map(|c| c.iter().any(|c| c.type_ == condition && c.status == "True"))
+        .unwrap_or(false)
+}
+
+fn is_failed(job: &Job) -> bool {
+    job_condition(job, "Failed")
+}
+
+fn is_finished(job: &Job) -> bool {
+    job_condition(job, "Complete") || is_failed(job)
+}
+
+fn start_time(job: &Job) -> Option<DateTime<Utc>> {
+    job.status.as_ref()?.start_time.as_ref().map(|t| t.0)
+}
+
+// Failed Jobs have no completion time, they end when the Failed condition is set
+fn end_time(job: &Job) -> Option<DateTime<Utc>> {
+    let status = job.status.as_ref()?;
+    status.completion_time.as_ref().map(|t| t.0).or_else(|| {
+        status
+            .conditions
+            .iter()
+            .flatten()
+            .find(|c| c.type_ == "Failed" && c.status == "True")
+            .and_then(|c| c.last_transition_time.as_ref())
+            .map(|t| t.0)
+    })
+}
+
+// Running time so far of active Jobs, finished Jobs without a known end are left out
+fn duration(job: &Job) -> Option<Duration> {
+    let end = match end_time(job) {
+        Some(end) => end,
+        None if is_finished(job) => return None,
+        None => Utc::now(),
+    };
+    Some(end - start_time(job)?)
+}
+
+// Longest expected gap between two runs of a cron schedule, erring on the long side
+fn schedule_period(schedule: &str) -> Option<Duration> {
+    let schedule = schedule.trim();
+    let macro_period = match schedule {
+        "@hourly" => Some(Duration::hours(1)),
+        "@daily" | "@midnight" => Some(Duration::days(1)),
+        "@weekly" => Some(Duration::weeks(1)),
+        "@monthly" => Some(Duration::days(31)),
+        "@yearly" | "@annually" => Some(Duration::days(366)),
+        _ => None,
+    };
+    if macro_period.is_some() {
+        return macro_period;
+    }
+
+    // Timezone prefixes don't change the period
+    let fields: Vec<&str> = schedule
+        .split_whitespace()
+        .filter(|f| !f.starts_with("CRON_TZ=") && !f.starts_with("TZ="))
+        .collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    let step = |field: &str| field.strip_prefix("*/").and_then(|s| s.parse::<i64>().ok());
+
+    let period = if fields[3] != "*" {
+        Duration::days(366)
+    } else if fields[2] != "*" {
+        Duration::days(31)
+    } else if fields[4] != "*" {
+        Duration::weeks(1)
+    } else if let Some(hours) = step(fields[1]) {
+        Duration::hours(hours)
+    } else if fields[1] != "*" {
+        Duration::days(1)
+    } else if let Some(minutes) = step(fields[0]) {
+        Duration::minutes(minutes)
+    } else if fields[0] != "*" {
+        Duration::hours(1)
+    } else {
+        Duration::minutes(1)
+    };
+    Some(period)
+}
+
+fn owned_jobs<'a>(state: &'a ClusterState, cronjob: &ObjectReference) -> Vec<&'a Job> {
+    let mut jobs: Vec<&Job> = state
+        .jobs
+        .iter()
+        .filter(|j| j.metadata.namespace.as_deref() == Some(cronjob.namespace.as_str()))
+        .filter(|j| {
+            j.metadata.owner_references.iter().flatten().any(|o| {
+                o.controller.unwrap_or(false)
+                    && o.kind == "CronJob"
+                    && o.name == cronjob.object_name
+            })
+        })
+        .collect();
+    // Most recent first
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.metadata.creation_timestamp.as_ref().map(|t| t.0)));
+    jobs
+}
+
+fn job_reference(job: &Job) -> Option<ObjectReference> {
+    Some(ObjectReference::new(
+        "Job",
+        job.metadata.name.as_deref()?,
+        job.metadata.namespace.as_deref()?,
+    ))
+}
+
+// Report failing, stalled and overlapping CronJobs, and Jobs running too long or never cleaned up
+pub struct JobsAnalyzer {
+    failed_runs: usize,
+    max_active_duration: Duration,
+}
+
+impl JobsAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            failed_runs: config::env_or("CRONJOB_FAILED_RUNS", 3),
+            max_active_duration: Duration::seconds(config::env_or("JOB_MAX_ACTIVE_SECONDS", 21600)),
+        }
+    }
+
+    fn stuck_job_issue(&self, job: &Job, owner: &ObjectReference) -> Option<Issue> {
+        let active = job.status.as_ref().and_then(|s| s.active).unwrap_or(0);
+        if active == 0 || is_finished(job) {
+            return None;
+        }
+        // Kubernetes enforces the deadline itself when set
+        if job
+            .spec
+            .as_ref()
+            .and_then(|s| s.active_deadline_seconds)
+            .is_some()
+        {
+            return None;
+        }
+        let running = duration(job)?;
+        if running <= self.max_active_duration {
+            return None;
+        }
+
+        let job_object = job_reference(job)?;
+        let mut issue = Issue::new(
+            owner,
+            IssueCategory::Reliability,
+            IssueSeverity::Medium,
+            "job-stuck-active",
+            format!(
+                "Job {} is active for {} hours without activeDeadlineSeconds",
+                job_object.object_name,
+                running.num_hours()
+            ),
+        )
+        .with_details(json!({
+            "active_pods": active,
+            "running_seconds": running.num_seconds(),
+            "max_active_seconds": self.max_active_duration.num_seconds(),
+        }));
+        if job_object != *owner {
+            issue = issue.with_linked_object(job_object);
+        }
+        Some(issue)
+    }
+
+    fn cronjob_issues(&self, state: &ClusterState, cronjob: &CronJob) -> Vec<Issue> {
+        let mut issues = vec![];
+        let (object, spec) = match (
+            cronjob.metadata.name.as_deref(),
+            cronjob.metadata.namespace.as_deref(),
+            cronjob.spec.as_ref(),
+        ) {
+            (Some(n), Some(ns), Some(s)) => (ObjectReference::new("CronJob", n, ns), s),
+            _ => return issues,
+        };
+        let jobs = owned_jobs(state, &object);
+
+        let finished: Vec<&Job> = jobs.iter().copied().filter(|j| is_finished(j)).collect();
+        let last_runs: Vec<&Job> = finished.iter().copied().take(self.failed_runs).collect();
+        if self.failed_runs > 0
+            && last_runs.len() == self.failed_runs
+            && last_runs.iter().all(|j| is_failed(j))
+        {
+            let names: Vec<&str> = last_runs
+                .iter()
+                .filter_map(|j| j.metadata.name.as_deref())
+                .collect();
+            issues.push(
+                Issue::new(
+                    &object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "cronjob-failing",
+                    format!("The last {} runs of the CronJob failed", self.failed_runs),
+                )
+                .with_details(json!({ "failed_jobs": names })),
+            );
+        }
+
+        for job in jobs.iter() {
+            issues.extend(self.stuck_job_issue(job, &object));
+        }
+
+        let period = match schedule_period(&spec.schedule) {
+            Some(p) => p,
+            None => return issues,
+        };
+
+        let last_schedule = cronjob
+            .status
+            .as_ref()
+            .and_then(|s| s.last_schedule_time.as_ref())
+            .or(cronjob.metadata.creation_timestamp.as_ref())
+            .map(|t| t.0);
+        let starting_deadline = Duration::seconds(spec.starting_deadline_seconds.unwrap_or(0));
+        if let (false, Some(last_schedule)) = (spec.suspend.unwrap_or(false), last_schedule) {
+            let late = Utc::now() - last_schedule;
+            if late > period * SCHEDULE_TOLERANCE + starting_deadline {
+                issues.push(
+                    Issue::new(
+                        &object,
+                        IssueCategory::Reliability,
+                        IssueSeverity::High,
+                        "cronjob-not-scheduling",
+                        format!(
+                            "CronJob was not scheduled for {} hours, its schedule {} implies runs at least every {} minutes",
+                            late.num_hours(),
+                            spec.schedule,
+                            period.num_minutes()
+                        ),
+                    )
+                    .with_details(json!({
+                        "schedule": spec.schedule,
+                        "last_schedule_time": last_schedule.to_rfc3339(),
+                    })),
+                );
+            }
+        }
+
+        // Runs outlasting the schedule period pile up when concurrent runs are allowed
+        let allows_concurrency = spec.concurrency_policy.as_deref().unwrap_or("Allow") == "Allow";
+        let longest_run = jobs.iter().filter_map(|j| duration(j)).max();
+        let concurrent_runs = cronjob
+            .status
+            .as_ref()
+            .and_then(|s| s.active.as_ref())
+            .map(Vec::len)
+            .unwrap_or(0);
+        if allows_concurrency
+            && (concurrent_runs > 1 || longest_run.map(|d| d > period).unwrap_or(false))
+        {
+            issues.push(
+                Issue::new(
+                    &object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::Medium,
+                    "cronjob-overlapping-runs",
+                    "CronJob runs last longer than its schedule period and are allowed to overlap"
+                        .to_string(),
+                )
+                .with_details(json!({
+                    "active_runs": concurrent_runs,
+                    "longest_run_seconds": longest_run.map(|d| d.num_seconds()),
+                    "period_seconds": period.num_seconds(),
+                })),
+            );
+        }
+
+        issues
+    }
+}
+
+impl Analyzer for JobsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/jobs"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["CronJob", "Job"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        for cronjob in state.cronjobs.iter() {
+            issues.extend(self.cronjob_issues(state, cronjob));
+        }
+
+        // Jobs created by CronJobs are cleaned up by their history limits
+        for job in state
+            .jobs
+            .iter()
+            .filter(|j| !state::has_controller(&j.metadata))
+        {
+            let object = match job_reference(job) {
+                Some(o) => o,
+                None => continue,
+            };
+            issues.extend(self.stuck_job_issue(job, &object));
+
+            let ttl = job.spec.as_ref().and_then(|s| s.ttl_seconds_after_finished);
+            if ttl.is_none() {
+                issues.push(Issue::new(
+                    &object,
+                    IssueCategory::Configuration,
+                    IssueSeverity::Low,
+                    "job-missing-ttl",
+                    "Job has no ttlSecondsAfterFinished, it and its pods are kept once finished"
+                        .to_string(),
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_macro_periods() {
+        assert_eq!(schedule_period("@hourly"), Some(Duration::hours(1)));
+        assert_eq!(schedule_period("@midnight"), Some(Duration::days(1)));
+        assert_eq!(schedule_period(" @weekly "), Some(Duration::weeks(1)));
+        assert_eq!(schedule_period("@yearly"), Some(Duration::days(366)));
+    }
+
+    #[test]
+    fn reads_steps() {
+        assert_eq!(schedule_period("*/15 * * * *"), Some(Duration::minutes(15)));
+        assert_eq!(schedule_period("0 */6 * * *"), Some(Duration::hours(6)));
+        assert_eq!(schedule_period("* * * * *"), Some(Duration::minutes(1)));
+    }
+
+    #[test]
+    fn takes_the_coarsest_fixed_field() {
+        assert_eq!(schedule_period("30 * * * *"), Some(Duration::hours(1)));
+        assert_eq!(schedule_period("0 2 * * *"), Some(Duration::days(1)));
+        assert_eq!(schedule_period("0 2 * * 1-5"), Some(Duration::weeks(1)));
+        assert_eq!(schedule_period("0 0 1 * *"), Some(Duration::days(31)));
+        assert_eq!(schedule_period("0 0 1 1 *"), Some(Duration::days(366)));
+    }
+
+    #[test]
+    fn ignores_timezones() {
+        assert_eq!(
+            schedule_period("CRON_TZ=Europe/Paris 0 2 * * *"),
+            Some(Duration::days(1))
+        );
+        assert_eq!(
+            schedule_period("TZ=UTC */5 * * * *"),
+            Some(Duration::minutes(5))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        assert_eq!(schedule_period(""), None);
+        assert_eq!(schedule_period("@reboot"), None);
+        assert_eq!(schedule_period("0 2 * *"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::events::EventStore;
+use crate::issues::Issue;
+use crate::state::ClusterState;
+
+mod availability;
+mod certificates;
+mod deprecated_apis;
+mod events;
+mod gitops;
+mod host_exposure;
+mod images;
+mod jobs;
+mod network_policies;
+mod orphans;
+mod pod_security_standards;
+mod pod_status;
+mod probes;
+mod rbac;
+mod resources;
+mod rollouts;
+mod security_context;
+mod service_accounts;
+mod services;
+mod storage;
+
+pub trait Analyzer: Send {
+    // Reported as the issues author
+    fn name(&self) -> &'static str;
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue>;
+
+    // Kinds of the cluster state the analysis reads, its issues are only resolved when all of them were listed
+    fn requires(&self) -> Vec<&'static str>;
+}
+
+// Warning event reasons the events analyzer has rules for
+pub fn watched_event_reasons() -> Vec<&'static str> {
+    events::RULES.iter().map(|r| r.reason).collect()
+}
+
+pub fn all(event_store: EventStore) -> Vec<Box<dyn Analyzer>> {
+    vec![
+        Box::new(gitops::GitOpsAnalyzer::from_env()),
+        Box::new(security_context::SecurityContextAnalyzer {}),
+        Box::new(host_exposure::HostExposureAnalyzer {}),
+        Box::new(pod_security_standards::PodSecurityStandardsAnalyzer {}),
+        Box::new(resources::ResourcesAnalyzer::from_env()),
+        Box::new(probes::ProbesAnalyzer::from_env()),
+        Box::new(availability::AvailabilityAnalyzer::from_env()),
+        Box::new(images::ImagesAnalyzer::from_env()),
+        Box::new(pod_status::PodStatusAnalyzer::from_env()),
+        Box::new(events::EventsAnalyzer::from_env(event_store)),
+        Box::new(services::ServicesAnalyzer {}),
+        Box::new(certificates::CertificatesAnalyzer::from_env()),
+        Box::new(orphans::OrphansAnalyzer::from_env()),
+        Box::new(storage::StorageAnalyzer::from_env()),
+        Box::new(rbac::RbacAnalyzer {}),
+        Box::new(service_accounts::ServiceAccountsAnalyzer {}),
+        Box::new(network_policies::NetworkPoliciesAnalyzer {}),
+        Box::new(deprecated_apis::DeprecatedApisAnalyzer::from_env()),
+        Box::new(jobs::JobsAnalyzer::from_env()),
+        Box::new(rollouts::RolloutsAnalyzer::from_env()),
+    ]
+}
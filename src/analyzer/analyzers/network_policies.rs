@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::networking::v1::{NetworkPolicy, NetworkPolicySpec};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::selectors;
+use crate::state::{self, ClusterState};
+
+const INGRESS: &str = "Ingress";
+const EGRESS: &str = "Egress";
+
+// Policy types default to Ingress, plus Egress when egress rules are set
+fn has_policy_type(spec: &NetworkPolicySpec, policy_type: &str) -> bool {
+    match spec.policy_types.as_ref() {
+        Some(types) => types.iter().any(|t| t == policy_type),
+        None => policy_type == INGRESS || (policy_type == EGRESS && spec.egress.is_some()),
+    }
+}
+
+fn selects_all_pods(spec: &NetworkPolicySpec) -> bool {
+    let selector = &spec.pod_selector;
+    selector
+        .match_labels
+        .as_ref()
+        .map(|l| l.is_empty())
+        .unwrap_or(true)
+        && selector
+            .match_expressions
+            .as_ref()
+            .map(|e| e.is_empty())
+            .unwrap_or(true)
+}
+
+fn is_default_deny_ingress(spec: &NetworkPolicySpec) -> bool {
+    selects_all_pods(spec)
+        && has_policy_type(spec, INGRESS)
+        && spec.ingress.as_ref().map(|i| i.is_empty()).unwrap_or(true)
+}
+
+// An egress rule without peers nor ports allows everything
+fn allows_all_egress(spec: &NetworkPolicySpec) -> bool {
+    spec.egress.iter().flatten().any(|rule| {
+        rule.to.as_ref().map(|t| t.is_empty()).unwrap_or(true)
+            && rule.ports.as_ref().map(|p| p.is_empty()).unwrap_or(true)
+    })
+}
+
+fn policy_reference(policy: &NetworkPolicy) -> Option<ObjectReference> {
+    Some(ObjectReference::new(
+        "NetworkPolicy",
+        policy.metadata.name.as_deref()?,
+        policy.metadata.namespace.as_deref()?,
+    ))
+}
+
+// Report gaps in network segmentation: namespaces open by default, pods no policy applies to, unrestricted egress
+pub struct NetworkPoliciesAnalyzer {}
+
+impl Analyzer for NetworkPoliciesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/network-policies"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            state::WORKLOAD_KINDS.as_slice(),
+            &["Namespace", "NetworkPolicy"],
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        let mut policies: BTreeMap<&str, Vec<(ObjectReference, &NetworkPolicySpec)>> =
+            BTreeMap::new();
+        for policy in state.network_policies.iter() {
+            if let (Some(object), Some(spec)) = (policy_reference(policy), policy.spec.as_ref()) {
+                policies
+                    .entry(policy.metadata.namespace.as_deref().unwrap_or_default())
+                    .or_default()
+                    .push((object, spec));
+            }
+        }
+
+        for namespace in state.namespaces.iter() {
+            let name = match namespace.metadata.name.as_deref() {
+                Some(n) => n,
+                None => continue,
+            };
+            let default_deny = policies
+                .get(name)
+                .map(|p| p.iter().any(|(_, spec)| is_default_deny_ingress(spec)))
+                .unwrap_or(false);
+            if !default_deny {
+                issues.push(Issue::new(
+                    &ObjectReference::namespace(name),
+                    IssueCategory::Security,
+                    IssueSeverity::Medium,
+                    "namespace-without-default-deny",
+                    "Namespace has no default deny NetworkPolicy, pods accept traffic from anywhere"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let workloads = state.workloads();
+        let no_policies = vec![];
+        for workload in workloads.iter() {
+            let selecting: Vec<&(ObjectReference, &NetworkPolicySpec)> = policies
+                .get(workload.object.namespace.as_str())
+                .unwrap_or(&no_policies)
+                .iter()
+                .filter(|(_, spec)| selectors::matches(&spec.pod_selector, workload.labels))
+                .collect();
+
+            if selecting.is_empty() {
+                issues.push(Issue::new(
+                    &workload.object,
+                    IssueCategory::Security,
+                    IssueSeverity::Low,
+                    "pods-not-selected-by-network-policy",
+                    "Pods are not selected by any NetworkPolicy".to_string(),
+                ));
+            }
+
+            let egress_policies: Vec<&(ObjectReference, &NetworkPolicySpec)> = selecting
+                .iter()
+                .copied()
+                .filter(|(_, spec)| has_policy_type(spec, EGRESS))
+                .collect();
+            let allowing_all: Vec<&str> = egress_policies
+                .iter()
+                .filter(|(_, spec)| allows_all_egress(spec))
+                .map(|(object, _)| object.object_name.as_str())
+                .collect();
+            // Pods no policy selects are already reported above
+            if !selecting.is_empty() && (egress_policies.is_empty() || !allowing_all.is_empty()) {
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Security,
+                        IssueSeverity::Low,
+                        "unrestricted-egress",
+                        "Pods can open connections to any destination".to_string(),
+                    )
+                    .with_details(json!({ "allowing_policies": allowing_all })),
+                );
+            }
+        }
+
+        // Policies matching no pod are likely stale or mistyped
+        for (namespace, namespace_policies) in policies.iter() {
+            for (object, spec) in namespace_policies.iter() {
+                let matched = workloads.iter().any(|w| {
+                    w.object.namespace == *namespace
+                        && selectors::matches(&spec.pod_selector, w.labels)
+                }) || state.pods.iter().any(|p| {
+                    p.metadata.namespace.as_deref() == Some(namespace)
+                        && selectors::matches(&spec.pod_selector, p.metadata.labels.as_ref())
+                });
+                if !matched {
+                    issues.push(
+                        Issue::new(
+                            object,
+                            IssueCategory::Configuration,
+                            IssueSeverity::Low,
+                            "network-policy-selects-nothing",
+                            "NetworkPolicy pod selector does not match any pod".to_string(),
+                        )
+                        .with_details(json!({ "pod_selector": spec.pod_selector })),
+                    );
+                }
+            }
+        }
+
+        issues
+    }
+}
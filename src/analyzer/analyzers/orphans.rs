@@ -0,0 +1,548 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use coi::quantity;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, PodSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::selectors;
+use crate::state::{self, ClusterState};
+
+// Managed by Kubernetes or tools, rather than referenced by pods
+const IGNORED_SECRET_TYPES: [&str; 3] = [
+    "kubernetes.io/service-account-token",
+    "bootstrap.kubernetes.io/token",
+    "helm.sh/release.v1",
+];
+const IGNORED_CONFIGMAPS: [&str; 1] = ["kube-root-ca.crt"];
+// Release storage labels of Helm 3 and Helm 2
+const RELEASE_LABELS: [(&str, &str); 2] = [("owner", "helm"), ("OWNER", "TILLER")];
+
+// Secrets cert-manager issuers read their ACME account key or CA from
+const ISSUER_SECRET_POINTERS: [&str; 2] =
+    ["/spec/acme/privateKeySecretRef/name", "/spec/ca/secretName"];
+
+const DEFAULT_REVISION_HISTORY_LIMIT: i32 = 10;
+
+type Key = (String, String);
+
+fn key(namespace: &str, name: &str) -> Key {
+    (namespace.to_string(), name.to_string())
+}
+
+// Objects referenced by pods, keyed by namespace and name
+#[derive(Default)]
+struct References {
+    configmaps: BTreeSet<Key>,
+    secrets: BTreeSet<Key>,
+    claims: BTreeSet<Key>,
+}
+
+impl References {
+    fn add_pod_spec(&mut self, namespace: &str, spec: &PodSpec) {
+        for volume in spec.volumes.iter().flatten() {
+            if let Some(name) = volume.config_map.as_ref().and_then(|c| c.name.as_deref()) {
+                self.configmaps.insert(key(namespace, name));
+            }
+            if let Some(name) = volume
+                .secret
+                .as_ref()
+                .and_then(|s| s.secret_name.as_deref())
+            {
+                self.secrets.insert(key(namespace, name));
+            }
+            if let Some(claim) = volume.persistent_volume_claim.as_ref() {
+                self.claims
+                    .insert(key(namespace, claim.claim_name.as_str()));
+            }
+            let sources = volume.projected.as_ref().and_then(|p| p.sources.as_ref());
+            for source in sources.iter().copied().flatten() {
+                if let Some(name) = source.config_map.as_ref().and_then(|c| c.name.as_deref()) {
+                    self.configmaps.insert(key(namespace, name));
+                }
+                if let Some(name) = source.secret.as_ref().and_then(|s| s.name.as_deref()) {
+                    self.secrets.insert(key(namespace, name));
+                }
+            }
+        }
+
+        let containers = spec
+            .init_containers
+            .iter()
+            .flatten()
+            .chain(spec.containers.iter());
+        for container in containers {
+            for source in container.env_from.iter().flatten() {
+                if let Some(name) = source
+                    .config_map_ref
+                    .as_ref()
+                    .and_then(|c| c.name.as_deref())
+                {
+                    self.configmaps.insert(key(namespace, name));
+                }
+                if let Some(name) = source.secret_ref.as_ref().and_then(|s| s.name.as_deref()) {
+                    self.secrets.insert(key(namespace, name));
+                }
+            }
+            let value_sources = container
+                .env
+                .iter()
+                .flatten()
+                .filter_map(|e| e.value_from.as_ref());
+            for source in value_sources {
+                let configmap = source.config_map_key_ref.as_ref();
+                if let Some(name) = configmap.and_then(|c| c.name.as_deref()) {
+                    self.configmaps.insert(key(namespace, name));
+                }
+                let secret = source.secret_key_ref.as_ref();
+                if let Some(name) = secret.and_then(|s| s.name.as_deref()) {
+                    self.secrets.insert(key(namespace, name));
+                }
+            }
+        }
+
+        for secret in spec.image_pull_secrets.iter().flatten() {
+            if let Some(name) = secret.name.as_deref() {
+                self.secrets.insert(key(namespace, name));
+            }
+        }
+    }
+
+    // ClusterIssuers secrets are read from the cert-manager cluster resource namespace
+    fn collect(state: &ClusterState, cluster_resource_namespace: &str) -> Self {
+        let mut references = Self::default();
+
+        // Templates cover scaled down workloads, pods cover the ones run by other controllers
+        for workload in state.workloads() {
+            references.add_pod_spec(&workload.object.namespace, workload.spec);
+        }
+        for pod in state.pods.iter() {
+            if let (Some(namespace), Some(spec)) =
+                (pod.metadata.namespace.as_deref(), pod.spec.as_ref())
+            {
+                references.add_pod_spec(namespace, spec);
+            }
+        }
+
+        for ingress in state.ingresses.iter() {
+            let namespace = match ingress.metadata.namespace.as_deref() {
+                Some(n) => n,
+                None => continue,
+            };
+            let tls = ingress.spec.as_ref().and_then(|s| s.tls.as_ref());
+            for name in tls
+                .iter()
+                .copied()
+                .flatten()
+                .filter_map(|t| t.secret_name.as_deref())
+            {
+                references.secrets.insert(key(namespace, name));
+            }
+        }
+
+        for account in state.service_accounts.iter() {
+            let namespace = match account.metadata.namespace.as_deref() {
+                Some(n) => n,
+                None => continue,
+            };
+            let secrets = account
+                .secrets
+                .iter()
+                .flatten()
+                .filter_map(|s| s.name.as_deref());
+            let pull_secrets = account
+                .image_pull_secrets
+                .iter()
+                .flatten()
+                .filter_map(|s| s.name.as_deref());
+            for name in secrets.chain(pull_secrets) {
+                references.secrets.insert(key(namespace, name));
+            }
+        }
+
+        for certificate in state.certificates.iter() {
+            let namespace = certificate.metadata.namespace.as_deref();
+            let secret = certificate
+                .data
+                .pointer("/spec/secretName")
+                .and_then(Value::as_str);
+            if let (Some(namespace), Some(name)) = (namespace, secret) {
+                references.secrets.insert(key(namespace, name));
+            }
+        }
+
+        for issuer in state.issuers.iter() {
+            let namespace = issuer
+                .metadata
+                .namespace
+                .as_deref()
+                .unwrap_or(cluster_resource_namespace);
+            for pointer in ISSUER_SECRET_POINTERS {
+                if let Some(name) = issuer.data.pointer(pointer).and_then(Value::as_str) {
+                    references.secrets.insert(key(namespace, name));
+                }
+            }
+        }
+
+        references
+    }
+}
+
+fn object_reference(kind: &str, metadata: &ObjectMeta) -> Option<ObjectReference> {
+    Some(ObjectReference::new(
+        kind,
+        metadata.name.as_deref()?,
+        metadata.namespace.as_deref()?,
+    ))
+}
+
+fn claim_capacity_bytes(claim: &PersistentVolumeClaim) -> f64 {
+    claim
+        .status
+        .as_ref()
+        .and_then(|s| s.capacity.as_ref())
+        .or_else(|| {
+            claim
+                .spec
+                .as_ref()
+                .and_then(|s| s.resources.as_ref())
+                .and_then(|r| r.requests.as_ref())
+        })
+        .and_then(|r| r.get("storage"))
+        .and_then(quantity::to_f64)
+        .unwrap_or(0.0)
+}
+
+fn is_helm_release(metadata: &ObjectMeta) -> bool {
+    let labels = metadata.labels.as_ref();
+    RELEASE_LABELS.iter().any(|(label, value)| {
+        labels.and_then(|l| l.get(*label)).map(String::as_str) == Some(*value)
+    })
+}
+
+// Report objects nothing uses anymore, which clutter namespaces and may cost storage
+pub struct OrphansAnalyzer {
+    ignored_namespaces: Vec<String>,
+    cert_manager_namespace: String,
+}
+
+impl OrphansAnalyzer {
+    pub fn from_env() -> Self {
+        let namespaces: String = config::env_or(
+            "ORPHANS_IGNORED_NAMESPACES",
+            "kube-system,kube-public,kube-node-lease".to_string(),
+        );
+        Self {
+            ignored_namespaces: namespaces
+                .split(',')
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty())
+                .collect(),
+            cert_manager_namespace: config::env_or(
+                "CERT_MANAGER_NAMESPACE",
+                "cert-manager".to_string(),
+            ),
+        }
+    }
+
+    // Objects owned by a controller, storing Helm releases or in system namespaces are not reported
+    fn is_candidate(&self, metadata: &ObjectMeta) -> bool {
+        let ignored = metadata
+            .namespace
+            .as_ref()
+            .map(|n| self.ignored_namespaces.contains(n))
+            .unwrap_or(true);
+        !ignored
+            && metadata.owner_references.iter().flatten().next().is_none()
+            && !is_helm_release(metadata)
+    }
+
+    fn orphan_issue(
+        object: &ObjectReference,
+        severity: IssueSeverity,
+        issue_tech_id: &str,
+        message: &str,
+    ) -> Issue {
+        Issue::new(
+            object,
+            IssueCategory::Configuration,
+            severity,
+            issue_tech_id,
+            message.to_string(),
+        )
+    }
+
+    // PVCs of StatefulSets replicas, including the ones scaled down
+    fn statefulset_claims(state: &ClusterState) -> BTreeMap<(&str, String), i32> {
+        let mut claims = BTreeMap::new();
+        for statefulset in state.statefulsets.iter() {
+            let (name, namespace, spec) = match (
+                statefulset.metadata.name.as_deref(),
+                statefulset.metadata.namespace.as_deref(),
+                statefulset.spec.as_ref(),
+            ) {
+                (Some(n), Some(ns), Some(s)) => (n, ns, s),
+                _ => continue,
+            };
+            for template in spec.volume_claim_templates.iter().flatten() {
+                if let Some(template_name) = template.metadata.name.as_deref() {
+                    let prefix = format!("{}-{}-", template_name, name);
+                    claims.insert((namespace, prefix), spec.replicas.unwrap_or(1));
+                }
+            }
+        }
+        claims
+    }
+
+    fn claim_issue(
+        state: &ClusterState,
+        references: &References,
+        statefulset_claims: &BTreeMap<(&str, String), i32>,
+        claim: &PersistentVolumeClaim,
+    ) -> Option<Issue> {
+        let object = object_reference("PersistentVolumeClaim", &claim.metadata)?;
+        if references
+            .claims
+            .contains(&key(&object.namespace, &object.object_name))
+        {
+            return None;
+        }
+
+        // Claims of existing StatefulSet replicas are kept for them, even when their pod is down
+        let replica_claim = statefulset_claims
+            .iter()
+            .any(|((namespace, prefix), replicas)| {
+                *namespace == object.namespace
+                    && object
+                        .object_name
+                        .strip_prefix(prefix.as_str())
+                        .and_then(|ordinal| ordinal.parse::<i32>().ok())
+                        .map(|ordinal| ordinal < *replicas)
+                        .unwrap_or(false)
+            });
+        if replica_claim {
+            return None;
+        }
+
+        let capacity_gib = claim_capacity_bytes(claim) / quantity::GIB;
+        Some(
+            Self::orphan_issue(
+                &object,
+                IssueSeverity::Medium,
+                "orphaned-persistent-volume-claim",
+                "PersistentVolumeClaim is not mounted by any pod",
+            )
+            .with_details(json!({
+                "capacity_gib": capacity_gib,
+                "estimated_monthly_cost": state.storage_gib_month_price.map(|p| capacity_gib * p),
+            })),
+        )
+    }
+}
+
+impl Analyzer for OrphansAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/orphans"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            state::WORKLOAD_KINDS.as_slice(),
+            &[
+                "ReplicaSet",
+                "ConfigMap",
+                "Secret",
+                "PersistentVolumeClaim",
+                "Service",
+                "Ingress",
+                "ServiceAccount",
+                "Certificate",
+                "Issuer",
+                "ClusterIssuer",
+            ],
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let references = References::collect(state, &self.cert_manager_namespace);
+        let mut issues = vec![];
+
+        for configmap in state.configmaps.iter() {
+            let object = match object_reference("ConfigMap", &configmap.metadata) {
+                Some(o) => o,
+                None => continue,
+            };
+            if !self.is_candidate(&configmap.metadata)
+                || IGNORED_CONFIGMAPS.contains(&object.object_name.as_str())
+                || references
+                    .configmaps
+                    .contains(&key(&object.namespace, &object.object_name))
+            {
+                continue;
+            }
+            issues.push(Self::orphan_issue(
+                &object,
+                IssueSeverity::Low,
+                "orphaned-configmap",
+                "ConfigMap is not referenced by any pod",
+            ));
+        }
+
+        for secret in state.secrets.iter() {
+            let object = match object_reference("Secret", &secret.metadata) {
+                Some(o) => o,
+                None => continue,
+            };
+            let ignored_type = secret
+                .type_
+                .as_deref()
+                .map(|t| IGNORED_SECRET_TYPES.contains(&t))
+                .unwrap_or(false);
+            if !self.is_candidate(&secret.metadata)
+                || ignored_type
+                || references
+                    .secrets
+                    .contains(&key(&object.namespace, &object.object_name))
+            {
+                continue;
+            }
+            issues.push(Self::orphan_issue(
+                &object,
+                IssueSeverity::Low,
+                "orphaned-secret",
+                "Secret is not referenced by any pod, ingress or service account",
+            ));
+        }
+
+        let statefulset_claims = Self::statefulset_claims(state);
+        for claim in state.persistent_volume_claims.iter() {
+            if !self.is_candidate(&claim.metadata) {
+                continue;
+            }
+            issues.extend(Self::claim_issue(
+                state,
+                &references,
+                &statefulset_claims,
+                claim,
+            ));
+        }
+
+        let workloads = state.workloads();
+        for service in state.services.iter() {
+            let object = match object_reference("Service", &service.metadata) {
+                Some(o) => o,
+                None => continue,
+            };
+            let selector = service
+                .spec
+                .as_ref()
+                .and_then(|s| s.selector.as_ref())
+                .filter(|s| !s.is_empty());
+            // Services without selector have their endpoints managed by something else
+            let selector = match selector {
+                Some(s) if self.is_candidate(&service.metadata) => s,
+                _ => continue,
+            };
+            let backed = workloads.iter().any(|w| {
+                w.object.namespace == object.namespace
+                    && selectors::matches_labels(selector, w.labels)
+            }) || state.pods.iter().any(|p| {
+                p.metadata.namespace.as_deref() == Some(object.namespace.as_str())
+                    && selectors::matches_labels(selector, p.metadata.labels.as_ref())
+            });
+            if !backed {
+                issues.push(
+                    Self::orphan_issue(
+                        &object,
+                        IssueSeverity::Low,
+                        "orphaned-service",
+                        "Service selector does not match any workload",
+                    )
+                    .with_details(json!({ "selector": selector })),
+                );
+            }
+        }
+
+        // Zero replicas ReplicaSets, left without Deployment or beyond its revision history
+        let mut old_replicasets: BTreeMap<ObjectReference, Vec<&ObjectMeta>> = BTreeMap::new();
+        for replicaset in state.replicasets.iter() {
+            let replicas = replicaset
+                .spec
+                .as_ref()
+                .and_then(|s| s.replicas)
+                .unwrap_or(1);
+            if replicas != 0 {
+                continue;
+            }
+            let object = match object_reference("ReplicaSet", &replicaset.metadata) {
+                Some(o) => o,
+                None => continue,
+            };
+            if !state::has_controller(&replicaset.metadata) {
+                issues.push(Self::orphan_issue(
+                    &object,
+                    IssueSeverity::Low,
+                    "orphaned-replicaset",
+                    "ReplicaSet has no replica and no Deployment",
+                ));
+                continue;
+            }
+            let deployment = replicaset
+                .metadata
+                .owner_references
+                .iter()
+                .flatten()
+                .find(|o| o.controller.unwrap_or(false) && o.kind == "Deployment");
+            if let Some(deployment) = deployment {
+                old_replicasets
+                    .entry(ObjectReference::new(
+                        "Deployment",
+                        &deployment.name,
+                        &object.namespace,
+                    ))
+                    .or_default()
+                    .push(&replicaset.metadata);
+            }
+        }
+
+        for (deployment, mut replicasets) in old_replicasets {
+            let limit = state
+                .deployments
+                .iter()
+                .find(|d| {
+                    d.metadata.name.as_deref() == Some(deployment.object_name.as_str())
+                        && d.metadata.namespace.as_deref() == Some(deployment.namespace.as_str())
+                })
+                .and_then(|d| d.spec.as_ref())
+                .and_then(|s| s.revision_history_limit)
+                .unwrap_or(DEFAULT_REVISION_HISTORY_LIMIT);
+            if replicasets.len() <= limit.max(0) as usize {
+                continue;
+            }
+
+            // Most recent revisions are the ones kept
+            replicasets.sort_by_key(|m| m.creation_timestamp.as_ref().map(|t| t.0));
+            let lingering = replicasets.len() - limit.max(0) as usize;
+            for metadata in replicasets.into_iter().take(lingering) {
+                if let Some(object) = object_reference("ReplicaSet", metadata) {
+                    issues.push(
+                        Self::orphan_issue(
+                            &object,
+                            IssueSeverity::Low,
+                            "lingering-replicaset",
+                            "ReplicaSet has no replica and exceeds the Deployment revision history",
+                        )
+                        .with_details(json!({ "revision_history_limit": limit }))
+                        .with_linked_object(deployment.clone()),
+                    );
+                }
+            }
+        }
+
+        issues
+    }
+}
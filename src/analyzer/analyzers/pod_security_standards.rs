@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{Container, PodSpec};
+use serde::Serialize;
+use serde_json::json;
+
+use super::security_context::{
+    added_capabilities, allows_privilege_escalation, is_privileged, seccomp_profile,
+};
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState, Workload};
+
+const ENFORCE_LABEL: &str = "pod-security.kubernetes.io/enforce";
+
+const BASELINE_CAPABILITIES: [&str; 13] = [
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+const BASELINE_SELINUX_TYPES: [&str; 3] = ["container_t", "container_init_t", "container_kvm_t"];
+const SAFE_SYSCTLS: [&str; 10] = [
+    "kernel.shm_rmid_forced",
+    "net.ipv4.ip_local_port_range",
+    "net.ipv4.ip_unprivileged_port_start",
+    "net.ipv4.tcp_syncookies",
+    "net.ipv4.ping_group_range",
+    "net.ipv4.ip_local_reserved_ports",
+    "net.ipv4.tcp_keepalive_time",
+    "net.ipv4.tcp_fin_timeout",
+    "net.ipv4.tcp_keepalive_intvl",
+    "net.ipv4.tcp_keepalive_probes",
+];
+const RESTRICTED_VOLUME_TYPES: [&str; 8] = [
+    "configMap",
+    "csi",
+    "downwardAPI",
+    "emptyDir",
+    "ephemeral",
+    "persistentVolumeClaim",
+    "projected",
+    "secret",
+];
+
+// Pod Security Standards levels, from the least to the most restrictive
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Privileged,
+    Baseline,
+    Restricted,
+}
+
+impl Level {
+    fn from_label(value: &str) -> Option<Self> {
+        match value {
+            "privileged" => Some(Level::Privileged),
+            "baseline" => Some(Level::Baseline),
+            "restricted" => Some(Level::Restricted),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Privileged => "privileged",
+            Level::Baseline => "baseline",
+            Level::Restricted => "restricted",
+        }
+    }
+}
+
+// A check failure, rejecting the workload from the given level
+struct Violation {
+    level: Level,
+    message: String,
+}
+
+fn baseline(message: String) -> Violation {
+    Violation {
+        level: Level::Baseline,
+        message,
+    }
+}
+
+fn restricted(message: String) -> Violation {
+    Violation {
+        level: Level::Restricted,
+        message,
+    }
+}
+
+fn pod_violations(spec: &PodSpec) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    if spec.host_network.unwrap_or(false) {
+        violations.push(baseline("hostNetwork is enabled".to_string()));
+    }
+    if spec.host_pid.unwrap_or(false) {
+        violations.push(baseline("hostPID is enabled".to_string()));
+    }
+    if spec.host_ipc.unwrap_or(false) {
+        violations.push(baseline("hostIPC is enabled".to_string()));
+    }
+
+    let sysctls = spec
+        .security_context
+        .as_ref()
+        .and_then(|s| s.sysctls.as_ref());
+    for sysctl in sysctls.iter().copied().flatten() {
+        if !SAFE_SYSCTLS.contains(&sysctl.name.as_str()) {
+            violations.push(baseline(format!("unsafe sysctl {}", sysctl.name)));
+        }
+    }
+
+    for volume in spec.volumes.iter().flatten() {
+        if volume.host_path.is_some() {
+            violations.push(baseline(format!("volume {} is a hostPath", volume.name)));
+            continue;
+        }
+        // Volume source is the only other field set on the volume
+        let volume_type = serde_json::to_value(volume)
+            .ok()
+            .and_then(|v| {
+                v.as_object()?
+                    .keys()
+                    .find(|k| k.as_str() != "name")
+                    .cloned()
+            })
+            .unwrap_or_default();
+        if !RESTRICTED_VOLUME_TYPES.contains(&volume_type.as_str()) {
+            violations.push(restricted(format!(
+                "volume {} has type {}",
+                volume.name, volume_type
+            )));
+        }
+    }
+
+    violations
+}
+
+fn container_violations(spec: &PodSpec, container: &Container) -> Vec<Violation> {
+    let mut violations = vec![];
+    let name = &container.name;
+    let context = container.security_context.as_ref();
+    let pod_context = spec.security_context.as_ref();
+
+    if is_privileged(container) {
+        violations.push(baseline(format!("container {} is privileged", name)));
+    }
+
+    for capability in added_capabilities(container) {
+        if !BASELINE_CAPABILITIES.contains(&capability) {
+            violations.push(baseline(format!(
+                "container {} adds capability {}",
+                name, capability
+            )));
+        } else if capability != "NET_BIND_SERVICE" {
+            violations.push(restricted(format!(
+                "container {} adds capability {}",
+                name, capability
+            )));
+        }
+    }
+
+    let drops_all = context
+        .and_then(|s| s.capabilities.as_ref())
+        .and_then(|c| c.drop.as_ref())
+        .map(|d| d.iter().any(|c| c == "ALL"))
+        .unwrap_or(false);
+    if !drops_all {
+        violations.push(restricted(format!(
+            "container {} does not drop ALL capabilities",
+            name
+        )));
+    }
+
+    for port in container.ports.iter().flatten() {
+        if port.host_port.unwrap_or(0) != 0 {
+            violations.push(baseline(format!("container {} uses a host port", name)));
+        }
+    }
+
+    let selinux = context
+        .and_then(|s| s.se_linux_options.as_ref())
+        .or_else(|| pod_context.and_then(|s| s.se_linux_options.as_ref()));
+    if let Some(options) = selinux {
+        let custom_type = options
+            .type_
+            .as_ref()
+            .map(|t| !t.is_empty() && !BASELINE_SELINUX_TYPES.contains(&t.as_str()))
+            .unwrap_or(false);
+        if custom_type || options.user.is_some() || options.role.is_some() {
+            violations.push(baseline(format!(
+                "container {} sets custom SELinux options",
+                name
+            )));
+        }
+    }
+
+    if context.and_then(|s| s.proc_mount.as_deref()) == Some("Unmasked") {
+        violations.push(baseline(format!(
+            "container {} uses an unmasked /proc mount",
+            name
+        )));
+    }
+
+    match seccomp_profile(spec, container) {
+        Some("Unconfined") => violations.push(baseline(format!(
+            "container {} uses an unconfined seccomp profile",
+            name
+        ))),
+        Some("RuntimeDefault") | Some("Localhost") => {}
+        _ => violations.push(restricted(format!(
+            "container {} does not set a seccomp profile",
+            name
+        ))),
+    }
+
+    if allows_privilege_escalation(container) {
+        violations.push(restricted(format!(
+            "container {} allows privilege escalation",
+            name
+        )));
+    }
+
+    let run_as_non_root = context
+        .and_then(|s| s.run_as_non_root)
+        .or_else(|| pod_context.and_then(|s| s.run_as_non_root));
+    if run_as_non_root != Some(true) {
+        violations.push(restricted(format!(
+            "container {} does not set runAsNonRoot",
+            name
+        )));
+    }
+
+    let run_as_user = context
+        .and_then(|s| s.run_as_user)
+        .or_else(|| pod_context.and_then(|s| s.run_as_user));
+    if run_as_user == Some(0) {
+        violations.push(restricted(format!("container {} runs as user 0", name)));
+    }
+
+    violations
+}
+
+fn violations(workload: &Workload) -> Vec<Violation> {
+    let mut violations = pod_violations(workload.spec);
+    for container in workload.containers() {
+        violations.extend(container_violations(workload.spec, container));
+    }
+    violations
+}
+
+// Most restrictive level the workload complies with
+fn compliant_level(violations: &[Violation]) -> Level {
+    violations
+        .iter()
+        .map(|v| match v.level {
+            Level::Restricted => Level::Baseline,
+            _ => Level::Privileged,
+        })
+        .min()
+        .unwrap_or(Level::Restricted)
+}
+
+// Evaluate workloads against Pod Security Standards and compare with their namespace enforcement
+pub struct PodSecurityStandardsAnalyzer {}
+
+impl Analyzer for PodSecurityStandardsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/pod-security-standards"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [state::WORKLOAD_KINDS.as_slice(), &["Namespace"]].concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        let mut enforced_levels: BTreeMap<String, Option<Level>> = BTreeMap::new();
+        for namespace in state.namespaces.iter() {
+            let name = match namespace.metadata.name.as_ref() {
+                Some(n) => n.clone(),
+                None => continue,
+            };
+            let enforced = namespace
+                .metadata
+                .labels
+                .as_ref()
+                .and_then(|l| l.get(ENFORCE_LABEL))
+                .and_then(|l| Level::from_label(l));
+            enforced_levels.insert(name, enforced);
+        }
+
+        // Most restrictive level every workload of the namespace complies with
+        let mut namespace_levels: BTreeMap<String, Level> = BTreeMap::new();
+
+        for workload in state.workloads() {
+            let violations = violations(&workload);
+            let level = compliant_level(&violations);
+            let namespace = &workload.object.namespace;
+            let namespace_level = namespace_levels
+                .entry(namespace.clone())
+                .or_insert(Level::Restricted);
+            *namespace_level = (*namespace_level).min(level);
+
+            let enforced = enforced_levels
+                .get(namespace)
+                .copied()
+                .flatten()
+                .unwrap_or(Level::Privileged);
+
+            let (issue_tech_id, severity, message, rejected_by) = if level < enforced {
+                (
+                    "pss-violates-enforced-level",
+                    IssueSeverity::High,
+                    format!(
+                        "Pods violate the enforced {} Pod Security Standard and won't be recreated",
+                        enforced.name()
+                    ),
+                    enforced,
+                )
+            } else if level < Level::Restricted {
+                let next = if level == Level::Privileged {
+                    Level::Baseline
+                } else {
+                    Level::Restricted
+                };
+                (
+                    "pss-rejected-if-raised",
+                    if next == Level::Baseline {
+                        IssueSeverity::Medium
+                    } else {
+                        IssueSeverity::Low
+                    },
+                    format!(
+                        "Pods would be rejected if the {} Pod Security Standard was enforced",
+                        next.name()
+                    ),
+                    next,
+                )
+            } else {
+                continue;
+            };
+
+            let reasons: Vec<&str> = violations
+                .iter()
+                .filter(|v| v.level <= rejected_by)
+                .map(|v| v.message.as_str())
+                .collect();
+            issues.push(
+                Issue::new(
+                    &workload.object,
+                    IssueCategory::Security,
+                    severity,
+                    issue_tech_id,
+                    message,
+                )
+                .with_details(json!({
+                    "compliant_level": level,
+                    "enforced_level": enforced,
+                    "violations": reasons,
+                })),
+            );
+        }
+
+        for (namespace, enforced) in enforced_levels {
+            if enforced.is_some() {
+                continue;
+            }
+
+            let recommended = namespace_levels
+                .get(&namespace)
+                .copied()
+                .unwrap_or(Level::Restricted);
+            issues.push(
+                Issue::new(
+                    &ObjectReference::namespace(&namespace),
+                    IssueCategory::Security,
+                    IssueSeverity::Medium,
+                    "pss-enforcement-missing",
+                    format!(
+                        "Namespace does not enforce any Pod Security Standard, its workloads comply with {}",
+                        recommended.name()
+                    ),
+                )
+                .with_details(json!({ "compliant_level": recommended })),
+            );
+        }
+
+        issues
+    }
+}
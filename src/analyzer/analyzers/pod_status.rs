@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{ContainerStateTerminated, ContainerStatus};
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState};
+
+const IMAGE_PULL_REASONS: [&str; 3] = ["ImagePullBackOff", "ErrImagePull", "InvalidImageName"];
+
+struct Rule {
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: &'static str,
+    // Whether the container status breaks the rule, given the restarts threshold
+    check: fn(&ContainerStatus, i32) -> bool,
+}
+
+const RULES: [Rule; 4] = [
+    Rule {
+        issue_tech_id: "crash-loop-backoff",
+        severity: IssueSeverity::High,
+        message: "Containers in CrashLoopBackOff",
+        check: |s, _| waiting_reason(s) == Some("CrashLoopBackOff"),
+    },
+    Rule {
+        issue_tech_id: "oom-killed",
+        severity: IssueSeverity::High,
+        message: "Containers repeatedly killed for exceeding their memory limit",
+        check: |s, _| {
+            s.restart_count > 1
+                && last_termination(s).and_then(|t| t.reason.as_deref()) == Some("OOMKilled")
+        },
+    },
+    Rule {
+        issue_tech_id: "image-pull-failure",
+        severity: IssueSeverity::High,
+        message: "Containers failing to pull their image",
+        check: |s, _| {
+            waiting_reason(s)
+                .map(|r| IMAGE_PULL_REASONS.contains(&r))
+                .unwrap_or(false)
+        },
+    },
+    Rule {
+        issue_tech_id: "high-restart-count",
+        severity: IssueSeverity::Medium,
+        message: "Containers restarting frequently",
+        check: |s, threshold| s.restart_count >= threshold,
+    },
+];
+
+fn waiting_reason(status: &ContainerStatus) -> Option<&str> {
+    status
+        .state
+        .as_ref()
+        .and_then(|s| s.waiting.as_ref())
+        .and_then(|w| w.reason.as_deref())
+}
+
+// Current termination, or the previous one when the container was restarted
+fn last_termination(status: &ContainerStatus) -> Option<&ContainerStateTerminated> {
+    status
+        .state
+        .as_ref()
+        .and_then(|s| s.terminated.as_ref())
+        .or_else(|| {
+            status
+                .last_state
+                .as_ref()
+                .and_then(|s| s.terminated.as_ref())
+        })
+}
+
+fn container_details(pod: &str, status: &ContainerStatus) -> Value {
+    let termination = last_termination(status);
+    json!({
+        "pod": pod,
+        "container": status.name,
+        "restart_count": status.restart_count,
+        "waiting_reason": waiting_reason(status),
+        "last_exit_code": termination.map(|t| t.exit_code),
+        "last_reason": termination.and_then(|t| t.reason.as_ref()),
+        "last_message": termination.and_then(|t| t.message.as_ref()),
+    })
+}
+
+// Report failing containers from pod statuses, on the controller owning the pods
+pub struct PodStatusAnalyzer {
+    restart_threshold: i32,
+}
+
+impl PodStatusAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            restart_threshold: config::env_or("RESTART_COUNT_THRESHOLD", 10),
+        }
+    }
+}
+
+impl Analyzer for PodStatusAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/pod-status"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [state::POD_OWNER_KINDS.as_slice(), &["Pod"]].concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        // Failing containers of every owner, by rule index
+        let mut findings: BTreeMap<(ObjectReference, usize), Vec<Value>> = BTreeMap::new();
+
+        for pod in state.pods.iter() {
+            let (owner, name, status) = match (
+                state.pod_owner(pod),
+                pod.metadata.name.as_deref(),
+                pod.status.as_ref(),
+            ) {
+                (Some(o), Some(n), Some(s)) => (o, n, s),
+                _ => continue,
+            };
+
+            let statuses = status
+                .init_container_statuses
+                .iter()
+                .flatten()
+                .chain(status.container_statuses.iter().flatten());
+            for container in statuses {
+                for (i, rule) in RULES.iter().enumerate() {
+                    if (rule.check)(container, self.restart_threshold) {
+                        findings
+                            .entry((owner.clone(), i))
+                            .or_default()
+                            .push(container_details(name, container));
+                    }
+                }
+            }
+        }
+
+        findings
+            .into_iter()
+            .map(|((owner, i), containers)| {
+                let rule = &RULES[i];
+                let names: Vec<String> = containers
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{}/{}",
+                            c["pod"].as_str().unwrap_or_default(),
+                            c["container"].as_str().unwrap_or_default()
+                        )
+                    })
+                    .collect();
+                Issue::new(
+                    &owner,
+                    IssueCategory::Reliability,
+                    rule.severity,
+                    rule.issue_tech_id,
+                    format!("{}: {}", rule.message, names.join(", ")),
+                )
+                .with_details(json!({
+                    "containers": containers,
+                    "restart_threshold": self.restart_threshold,
+                }))
+            })
+            .collect()
+    }
+}
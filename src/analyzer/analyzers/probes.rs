@@ -0,0 +1,218 @@
+use k8s_openapi::api::core::v1::{Container, Probe};
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::state::{self, ClusterState, Workload};
+
+// Only long-running workloads are expected to be probed
+const PROBED_KINDS: [&str; 2] = ["Deployment", "StatefulSet"];
+
+// Kubernetes defaults for unset probe fields
+const DEFAULT_PERIOD_SECONDS: i32 = 10;
+const DEFAULT_FAILURE_THRESHOLD: i32 = 3;
+const DEFAULT_TIMEOUT_SECONDS: i32 = 1;
+
+struct Rule {
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: &'static str,
+}
+
+const MISSING_READINESS_PROBE: Rule = Rule {
+    issue_tech_id: "missing-readiness-probe",
+    severity: IssueSeverity::Medium,
+    message: "Containers without readiness probe receive traffic before being ready",
+};
+const MISSING_LIVENESS_PROBE: Rule = Rule {
+    issue_tech_id: "missing-liveness-probe",
+    severity: IssueSeverity::Low,
+    message: "Containers without liveness probe are not restarted when stuck",
+};
+const LIVENESS_SAME_AS_READINESS: Rule = Rule {
+    issue_tech_id: "liveness-same-as-readiness",
+    severity: IssueSeverity::Medium,
+    message:
+        "Containers restart on temporary unreadiness, liveness probe is identical to readiness",
+};
+const AGGRESSIVE_LIVENESS_PROBE: Rule = Rule {
+    issue_tech_id: "aggressive-liveness-probe",
+    severity: IssueSeverity::Medium,
+    message: "Containers may be restarted on transient slowness, liveness probe is too aggressive",
+};
+const MISSING_STARTUP_PROBE: Rule = Rule {
+    issue_tech_id: "missing-startup-probe",
+    severity: IssueSeverity::Low,
+    message: "Slow starting containers rely on liveness initial delay instead of a startup probe",
+};
+
+// Report missing or misconfigured container probes
+pub struct ProbesAnalyzer {
+    // Seconds a liveness probe should tolerate failures for before restarting
+    min_liveness_failure_window: i32,
+    // Seconds a liveness probe should wait for an answer, the Kubernetes default is accepted unless raised
+    min_liveness_timeout: i32,
+    // Liveness initial delay from which a container is considered slow to start
+    slow_start_delay: i32,
+}
+
+impl ProbesAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            min_liveness_failure_window: config::env_or("LIVENESS_MIN_FAILURE_WINDOW", 15),
+            min_liveness_timeout: config::env_or("LIVENESS_MIN_TIMEOUT", DEFAULT_TIMEOUT_SECONDS),
+            slow_start_delay: config::env_or("SLOW_START_DELAY", 30),
+        }
+    }
+
+    // A timeout reaching the period leaves no pause between probes of a slow container
+    fn is_aggressive(&self, liveness: &Probe) -> bool {
+        let failure_threshold = liveness
+            .failure_threshold
+            .unwrap_or(DEFAULT_FAILURE_THRESHOLD);
+        let period = liveness.period_seconds.unwrap_or(DEFAULT_PERIOD_SECONDS);
+        let timeout = liveness.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+        failure_threshold <= 1
+            || failure_threshold * period < self.min_liveness_failure_window
+            || timeout < self.min_liveness_timeout
+            || timeout >= period
+    }
+
+    fn violated_rules(&self, container: &Container) -> Vec<&'static Rule> {
+        let mut rules = vec![];
+        let readiness = container.readiness_probe.as_ref();
+        let liveness = container.liveness_probe.as_ref();
+
+        if readiness.is_none() {
+            rules.push(&MISSING_READINESS_PROBE);
+        }
+        let liveness = match liveness {
+            Some(l) => l,
+            None => {
+                rules.push(&MISSING_LIVENESS_PROBE);
+                return rules;
+            }
+        };
+
+        if readiness == Some(liveness) {
+            rules.push(&LIVENESS_SAME_AS_READINESS);
+        }
+        if self.is_aggressive(liveness) {
+            rules.push(&AGGRESSIVE_LIVENESS_PROBE);
+        }
+        if container.startup_probe.is_none()
+            && liveness.initial_delay_seconds.unwrap_or(0) >= self.slow_start_delay
+        {
+            rules.push(&MISSING_STARTUP_PROBE);
+        }
+
+        rules
+    }
+
+    fn workload_issues(&self, workload: &Workload) -> Vec<Issue> {
+        let mut findings: Vec<(&Rule, Vec<&Container>)> = vec![];
+
+        // Init containers run to completion and are never probed
+        for container in workload.spec.containers.iter() {
+            for rule in self.violated_rules(container) {
+                match findings
+                    .iter_mut()
+                    .find(|(r, _)| r.issue_tech_id == rule.issue_tech_id)
+                {
+                    Some((_, containers)) => containers.push(container),
+                    None => findings.push((rule, vec![container])),
+                }
+            }
+        }
+
+        findings
+            .into_iter()
+            .map(|(rule, containers)| {
+                let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+                let mut details = json!({ "containers": names });
+                if rule.issue_tech_id == AGGRESSIVE_LIVENESS_PROBE.issue_tech_id {
+                    details["liveness_probes"] = containers
+                        .iter()
+                        .map(|c| self.liveness_details(c))
+                        .collect();
+                }
+                Issue::new(
+                    &workload.object,
+                    IssueCategory::Reliability,
+                    rule.severity,
+                    rule.issue_tech_id,
+                    format!("{}: {}", rule.message, names.join(", ")),
+                )
+                .with_details(details)
+            })
+            .collect()
+    }
+
+    // Effective liveness settings of a container, and the bounds they are checked against
+    fn liveness_details(&self, container: &Container) -> Value {
+        let liveness = container.liveness_probe.as_ref();
+        json!({
+            "container": container.name,
+            "timeout_seconds": liveness
+                .and_then(|l| l.timeout_seconds)
+                .unwrap_or(DEFAULT_TIMEOUT_SECONDS),
+            "period_seconds": liveness
+                .and_then(|l| l.period_seconds)
+                .unwrap_or(DEFAULT_PERIOD_SECONDS),
+            "failure_threshold": liveness
+                .and_then(|l| l.failure_threshold)
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            "min_timeout_seconds": self.min_liveness_timeout,
+            "min_failure_window_seconds": self.min_liveness_failure_window,
+        })
+    }
+}
+
+impl Analyzer for ProbesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/probes"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        state::WORKLOAD_KINDS.to_vec()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        state
+            .workloads()
+            .iter()
+            .filter(|w| PROBED_KINDS.contains(&w.object.object_type.as_str()))
+            .flat_map(|w| self.workload_issues(w))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer() -> ProbesAnalyzer {
+        ProbesAnalyzer {
+            min_liveness_failure_window: 15,
+            min_liveness_timeout: 2,
+            slow_start_delay: 30,
+        }
+    }
+
+    fn liveness(timeout_seconds: Option<i32>, period_seconds: Option<i32>) -> Probe {
+        Probe {
+            timeout_seconds,
+            period_seconds,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flags_liveness_timeouts() {
+        let analyzer = analyzer();
+        assert!(analyzer.is_aggressive(&liveness(None, None)));
+        assert!(analyzer.is_aggressive(&liveness(Some(10), Some(10))));
+        assert!(!analyzer.is_aggressive(&liveness(Some(3), Some(10))));
+    }
+}
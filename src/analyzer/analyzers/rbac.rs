@@ -0,0 +1,306 @@
+use k8s_openapi::api::rbac::v1::{PolicyRule, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::ClusterState;
+
+// Label of the roles and bindings Kubernetes creates itself
+const BOOTSTRAPPING_LABEL: &str = "kubernetes.io/bootstrapping";
+const CLUSTER_ADMIN: &str = "cluster-admin";
+const ANONYMOUS_SUBJECTS: [&str; 2] = ["system:anonymous", "system:unauthenticated"];
+const AUTHENTICATED_GROUP: &str = "system:authenticated";
+const ESCALATION_VERBS: [&str; 3] = ["escalate", "bind", "impersonate"];
+const READ_VERBS: [&str; 4] = ["get", "list", "watch", "*"];
+
+struct Rule {
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: &'static str,
+    // Whether the policy rule breaks the rule
+    check: fn(&PolicyRule) -> bool,
+}
+
+fn contains(values: Option<&Vec<String>>, candidates: &[&str]) -> bool {
+    values
+        .map(|v| v.iter().any(|v| candidates.contains(&v.as_str())))
+        .unwrap_or(false)
+}
+
+const RULES: [Rule; 4] = [
+    Rule {
+        issue_tech_id: "rbac-wildcard-verbs",
+        severity: IssueSeverity::High,
+        message: "Role grants every verb",
+        check: |r| r.verbs.iter().any(|v| v == "*"),
+    },
+    Rule {
+        issue_tech_id: "rbac-wildcard-resources",
+        severity: IssueSeverity::High,
+        message: "Role grants access to every resource",
+        check: |r| contains(r.resources.as_ref(), &["*"]),
+    },
+    Rule {
+        issue_tech_id: "rbac-secrets-read",
+        severity: IssueSeverity::Medium,
+        message: "Role grants read access to secrets",
+        check: |r| {
+            contains(r.resources.as_ref(), &["secrets", "*"])
+                && contains(r.api_groups.as_ref(), &["", "*"])
+                && contains(Some(&r.verbs), &READ_VERBS)
+        },
+    },
+    Rule {
+        issue_tech_id: "rbac-privilege-escalation",
+        severity: IssueSeverity::High,
+        message: "Role grants escalate, bind or impersonate permissions",
+        check: |r| contains(Some(&r.verbs), &ESCALATION_VERBS),
+    },
+];
+
+const SECRETS_READ_TECH_ID: &str = "rbac-secrets-read";
+
+pub(super) fn is_bootstrapped(metadata: &ObjectMeta) -> bool {
+    metadata
+        .labels
+        .as_ref()
+        .map(|l| l.contains_key(BOOTSTRAPPING_LABEL))
+        .unwrap_or(false)
+}
+
+// Binding of subjects to a role
+struct Binding<'a> {
+    object: ObjectReference,
+    role_ref: &'a RoleRef,
+    subjects: &'a [Subject],
+    cluster_wide: bool,
+    bootstrapped: bool,
+}
+
+impl<'a> Binding<'a> {
+    fn binds(&self, role: &ObjectReference) -> bool {
+        self.role_ref.kind == role.object_type
+            && self.role_ref.name == role.object_name
+            && (role.object_type == "ClusterRole" || self.object.namespace == role.namespace)
+    }
+
+    fn subjects_details(&self) -> Vec<Value> {
+        self.subjects
+            .iter()
+            .map(|s| {
+                json!({
+                    "kind": s.kind,
+                    "name": s.name,
+                    "namespace": s.namespace,
+                    "binding": self.object.object_name,
+                    "binding_namespace": self.object.namespace,
+                })
+            })
+            .collect()
+    }
+}
+
+fn bindings(state: &ClusterState) -> Vec<Binding<'_>> {
+    let mut bindings = vec![];
+    for binding in state.role_bindings.iter() {
+        let (name, namespace) = match (
+            binding.metadata.name.as_deref(),
+            binding.metadata.namespace.as_deref(),
+        ) {
+            (Some(n), Some(ns)) => (n, ns),
+            _ => continue,
+        };
+        bindings.push(Binding {
+            object: ObjectReference::new("RoleBinding", name, namespace),
+            role_ref: &binding.role_ref,
+            subjects: binding.subjects.as_deref().unwrap_or_default(),
+            cluster_wide: false,
+            bootstrapped: is_bootstrapped(&binding.metadata),
+        });
+    }
+    for binding in state.cluster_role_bindings.iter() {
+        let name = match binding.metadata.name.as_deref() {
+            Some(n) => n,
+            None => continue,
+        };
+        bindings.push(Binding {
+            object: ObjectReference::cluster_scoped("ClusterRoleBinding", name),
+            role_ref: &binding.role_ref,
+            subjects: binding.subjects.as_deref().unwrap_or_default(),
+            cluster_wide: true,
+            bootstrapped: is_bootstrapped(&binding.metadata),
+        });
+    }
+    bindings
+}
+
+fn binding_issues(binding: &Binding) -> Vec<Issue> {
+    let mut issues = vec![];
+    if binding.bootstrapped {
+        return issues;
+    }
+
+    let anonymous: Vec<&str> = binding
+        .subjects
+        .iter()
+        .map(|s| s.name.as_str())
+        .filter(|n| ANONYMOUS_SUBJECTS.contains(n))
+        .collect();
+    if !anonymous.is_empty() {
+        issues.push(
+            Issue::new(
+                &binding.object,
+                IssueCategory::Security,
+                IssueSeverity::Critical,
+                "rbac-anonymous-binding",
+                format!(
+                    "Role {} is granted to unauthenticated users",
+                    binding.role_ref.name
+                ),
+            )
+            .with_details(json!({ "subjects": anonymous, "role": binding.role_ref.name })),
+        );
+    }
+
+    let service_accounts: Vec<Value> = binding
+        .subjects
+        .iter()
+        .filter(|s| s.kind == "ServiceAccount")
+        .map(|s| json!({ "name": s.name, "namespace": s.namespace }))
+        .collect();
+    if binding.role_ref.kind == "ClusterRole"
+        && binding.role_ref.name == CLUSTER_ADMIN
+        && !service_accounts.is_empty()
+    {
+        issues.push(
+            Issue::new(
+                &binding.object,
+                IssueCategory::Security,
+                if binding.cluster_wide {
+                    IssueSeverity::Critical
+                } else {
+                    IssueSeverity::High
+                },
+                "rbac-serviceaccount-cluster-admin",
+                "ServiceAccounts are granted cluster-admin".to_string(),
+            )
+            .with_details(json!({ "service_accounts": service_accounts })),
+        );
+    }
+
+    issues
+}
+
+// Issues are reported on the role, or on the binding when the role is a built-in one
+fn role_issues(
+    object: &ObjectReference,
+    rules: &[PolicyRule],
+    bindings: &[&Binding],
+) -> Vec<Issue> {
+    let subjects: Vec<Value> = bindings.iter().flat_map(|b| b.subjects_details()).collect();
+    let cluster_wide = bindings.iter().any(|b| b.cluster_wide);
+    let authenticated = bindings
+        .iter()
+        .flat_map(|b| b.subjects.iter())
+        .any(|s| s.name == AUTHENTICATED_GROUP);
+
+    RULES
+        .iter()
+        .filter_map(|rule| {
+            let offending: Vec<&PolicyRule> = rules.iter().filter(|r| (rule.check)(r)).collect();
+            if offending.is_empty() {
+                return None;
+            }
+            // Granted to every user, or secrets readable in every namespace, is what makes it broad
+            let severity = if authenticated {
+                IssueSeverity::Critical
+            } else if rule.issue_tech_id == SECRETS_READ_TECH_ID && cluster_wide {
+                IssueSeverity::High
+            } else {
+                rule.severity
+            };
+            Some(
+                Issue::new(
+                    object,
+                    IssueCategory::Security,
+                    severity,
+                    rule.issue_tech_id,
+                    format!("{} to {} subjects", rule.message, subjects.len()),
+                )
+                .with_details(json!({
+                    "rules": offending,
+                    "subjects": subjects,
+                    "cluster_wide": cluster_wide,
+                    "authenticated_users": authenticated,
+                })),
+            )
+        })
+        .collect()
+}
+
+// Report risky permissions of bound roles, and risky bindings, along with the subjects affected
+pub struct RbacAnalyzer {}
+
+impl Analyzer for RbacAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/rbac"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["Role", "ClusterRole", "RoleBinding", "ClusterRoleBinding"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let bindings = bindings(state);
+        let mut issues: Vec<Issue> = bindings.iter().flat_map(binding_issues).collect();
+
+        let mut roles = vec![];
+        for role in state.roles.iter() {
+            if let (Some(name), Some(namespace)) = (
+                role.metadata.name.as_deref(),
+                role.metadata.namespace.as_deref(),
+            ) {
+                roles.push((
+                    ObjectReference::new("Role", name, namespace),
+                    &role.metadata,
+                    role.rules.as_deref().unwrap_or_default(),
+                ));
+            }
+        }
+        for role in state.cluster_roles.iter() {
+            if let Some(name) = role.metadata.name.as_deref() {
+                roles.push((
+                    ObjectReference::cluster_scoped("ClusterRole", name),
+                    &role.metadata,
+                    role.rules.as_deref().unwrap_or_default(),
+                ));
+            }
+        }
+
+        for (role, metadata, rules) in roles {
+            let role_bindings: Vec<&Binding> = bindings
+                .iter()
+                .filter(|b| b.binds(&role) && !b.subjects.is_empty())
+                .collect();
+            if role_bindings.is_empty() {
+                continue;
+            }
+
+            // Built-in roles are meant to be powerful, granting them is what gets reported
+            if !is_bootstrapped(metadata) {
+                issues.extend(role_issues(&role, rules, &role_bindings));
+                continue;
+            }
+            for binding in role_bindings.into_iter().filter(|b| !b.bootstrapped) {
+                issues.extend(
+                    role_issues(&binding.object, rules, &[binding])
+                        .into_iter()
+                        .map(|i| i.with_linked_object(role.clone())),
+                );
+            }
+        }
+
+        issues
+    }
+}
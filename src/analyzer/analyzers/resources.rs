@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use coi::resources::ResourceAmounts;
+use k8s_openapi::api::core::v1::{Container, LimitRange};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState, Workload};
+
+struct Rule {
+    issue_tech_id: &'static str,
+    category: IssueCategory,
+    severity: IssueSeverity,
+    message: &'static str,
+}
+
+const MISSING_CPU_REQUEST: Rule = Rule {
+    issue_tech_id: "missing-cpu-request",
+    category: IssueCategory::Performance,
+    severity: IssueSeverity::Medium,
+    message: "Containers without CPU request",
+};
+const MISSING_MEMORY_REQUEST: Rule = Rule {
+    issue_tech_id: "missing-memory-request",
+    category: IssueCategory::Reliability,
+    severity: IssueSeverity::Medium,
+    message: "Containers without memory request",
+};
+const MISSING_MEMORY_LIMIT: Rule = Rule {
+    issue_tech_id: "missing-memory-limit",
+    category: IssueCategory::Reliability,
+    severity: IssueSeverity::Medium,
+    message: "Containers without memory limit",
+};
+const LIMITS_FAR_ABOVE_REQUESTS: Rule = Rule {
+    issue_tech_id: "limits-far-above-requests",
+    category: IssueCategory::Performance,
+    severity: IssueSeverity::Low,
+    message: "Containers with limits far above their requests",
+};
+
+#[derive(Default)]
+struct ContainerResources {
+    requests: ResourceAmounts,
+    limits: ResourceAmounts,
+}
+
+impl ContainerResources {
+    // Resources as admitted by Kubernetes: a missing request equals the limit, then LimitRange defaults apply
+    fn new(container: &Container, defaults: Option<&ContainerResources>) -> Self {
+        let resources = container.resources.as_ref();
+        let requests =
+            ResourceAmounts::from_resource_list(resources.and_then(|r| r.requests.as_ref()));
+        let mut limits =
+            ResourceAmounts::from_resource_list(resources.and_then(|r| r.limits.as_ref()));
+        let mut requests = requests.or(&limits);
+        if let Some(defaults) = defaults {
+            limits = limits.or(&defaults.limits);
+            requests = requests.or(&defaults.requests);
+        }
+        Self { requests, limits }
+    }
+
+    // Container defaults set by the LimitRange, default limits also being default requests
+    fn from_limit_range(limit_range: &LimitRange) -> Self {
+        let mut defaults = Self::default();
+        let items = limit_range.spec.as_ref().map(|s| s.limits.iter());
+        for item in items
+            .into_iter()
+            .flatten()
+            .filter(|i| i.type_ == "Container")
+        {
+            let limits = ResourceAmounts::from_resource_list(item.default.as_ref());
+            let requests = ResourceAmounts::from_resource_list(item.default_request.as_ref());
+            defaults.limits = defaults.limits.or(&limits);
+            defaults.requests = defaults.requests.or(&requests.or(&limits));
+        }
+        defaults
+    }
+
+    fn sets_defaults(&self) -> bool {
+        self.requests != ResourceAmounts::default() || self.limits != ResourceAmounts::default()
+    }
+}
+
+// Report containers and namespaces without resource boundaries
+pub struct ResourcesAnalyzer {
+    // Limits over requests ratio above which containers are reported
+    max_limit_ratio: f64,
+}
+
+impl ResourcesAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            max_limit_ratio: config::env_or("MAX_LIMIT_REQUEST_RATIO", 4.0),
+        }
+    }
+
+    fn limit_ratio_exceeded(&self, requested: f64, limit: f64) -> bool {
+        requested > 0.0 && limit / requested > self.max_limit_ratio
+    }
+
+    fn container_issues(
+        &self,
+        workload: &Workload,
+        defaults: Option<&ContainerResources>,
+    ) -> Vec<Issue> {
+        let mut findings: Vec<(&Rule, Vec<&str>)> = vec![
+            (&MISSING_CPU_REQUEST, vec![]),
+            (&MISSING_MEMORY_REQUEST, vec![]),
+            (&MISSING_MEMORY_LIMIT, vec![]),
+            (&LIMITS_FAR_ABOVE_REQUESTS, vec![]),
+        ];
+
+        for container in workload.containers() {
+            let resources = ContainerResources::new(container, defaults);
+            let name = container.name.as_str();
+            if resources.requests.cpu_cores <= 0.0 {
+                findings[0].1.push(name);
+            }
+            if resources.requests.memory_bytes <= 0.0 {
+                findings[1].1.push(name);
+            }
+            if resources.limits.memory_bytes <= 0.0 {
+                findings[2].1.push(name);
+            }
+            if self.limit_ratio_exceeded(resources.requests.cpu_cores, resources.limits.cpu_cores)
+                || self.limit_ratio_exceeded(
+                    resources.requests.memory_bytes,
+                    resources.limits.memory_bytes,
+                )
+            {
+                findings[3].1.push(name);
+            }
+        }
+
+        findings
+            .into_iter()
+            .filter(|(_, containers)| !containers.is_empty())
+            .map(|(rule, containers)| {
+                let mut details = json!({ "containers": containers });
+                if rule.issue_tech_id == LIMITS_FAR_ABOVE_REQUESTS.issue_tech_id {
+                    details["max_limit_request_ratio"] = json!(self.max_limit_ratio);
+                }
+                Issue::new(
+                    &workload.object,
+                    rule.category,
+                    rule.severity,
+                    rule.issue_tech_id,
+                    format!("{}: {}", rule.message, containers.join(", ")),
+                )
+                .with_details(details)
+            })
+            .collect()
+    }
+}
+
+impl Analyzer for ResourcesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/resources"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            state::WORKLOAD_KINDS.as_slice(),
+            &["Namespace", "LimitRange", "ResourceQuota"],
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        // Containers of namespaces with LimitRange defaults get the resources they don't set
+        let mut defaults: BTreeMap<&str, ContainerResources> = BTreeMap::new();
+        for limit_range in state.limit_ranges.iter() {
+            let namespace = match limit_range.metadata.namespace.as_deref() {
+                Some(n) => n,
+                None => continue,
+            };
+            let range_defaults = ContainerResources::from_limit_range(limit_range);
+            let namespace_defaults = defaults.entry(namespace).or_default();
+            namespace_defaults.requests = namespace_defaults.requests.or(&range_defaults.requests);
+            namespace_defaults.limits = namespace_defaults.limits.or(&range_defaults.limits);
+        }
+        defaults.retain(|_, d| d.sets_defaults());
+
+        for workload in state.workloads() {
+            let namespace_defaults = defaults.get(workload.object.namespace.as_str());
+            issues.extend(self.container_issues(&workload, namespace_defaults));
+        }
+
+        let with_quota: BTreeSet<&str> = state
+            .resource_quotas
+            .iter()
+            .filter_map(|q| q.metadata.namespace.as_deref())
+            .collect();
+
+        for namespace in state.namespaces.iter() {
+            let name = match namespace.metadata.name.as_deref() {
+                Some(n) => n,
+                None => continue,
+            };
+            let object = ObjectReference::namespace(name);
+
+            if !defaults.contains_key(name) {
+                issues.push(Issue::new(
+                    &object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::Low,
+                    "namespace-without-limitrange",
+                    "Namespace has no LimitRange defaulting container resources".to_string(),
+                ));
+            }
+            if !with_quota.contains(name) {
+                issues.push(Issue::new(
+                    &object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::Low,
+                    "namespace-without-resourcequota",
+                    "Namespace has no ResourceQuota bounding its consumption".to_string(),
+                ));
+            }
+        }
+
+        issues
+    }
+}
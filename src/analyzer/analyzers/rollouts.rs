@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::ClusterState;
+
+const PROGRESS_DEADLINE_EXCEEDED: &str = "ProgressDeadlineExceeded";
+
+struct Problem {
+    object: ObjectReference,
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: String,
+    details: Value,
+    // Whether the problem is reported right away, Kubernetes having already waited for it
+    immediate: bool,
+    // Transition time of the condition reporting the problem, when Kubernetes sets one
+    since: Option<DateTime<Utc>>,
+}
+
+fn reference(kind: &str, name: Option<&str>, namespace: Option<&str>) -> Option<ObjectReference> {
+    Some(ObjectReference::new(kind, name?, namespace?))
+}
+
+fn deployment_problems(deployment: &Deployment) -> Vec<Problem> {
+    let mut problems = vec![];
+    let (object, status) = match (
+        reference(
+            "Deployment",
+            deployment.metadata.name.as_deref(),
+            deployment.metadata.namespace.as_deref(),
+        ),
+        deployment.status.as_ref(),
+    ) {
+        (Some(o), Some(s)) => (o, s),
+        _ => return problems,
+    };
+    // Paused rollouts are not expected to progress
+    if deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.paused)
+        .unwrap_or(false)
+    {
+        return problems;
+    }
+
+    let details = json!({
+        "replicas": status.replicas,
+        "updated_replicas": status.updated_replicas,
+        "ready_replicas": status.ready_replicas,
+        "available_replicas": status.available_replicas,
+        "unavailable_replicas": status.unavailable_replicas,
+        "observed_generation": status.observed_generation,
+        "generation": deployment.metadata.generation,
+    });
+
+    let progressing = status
+        .conditions
+        .iter()
+        .flatten()
+        .find(|c| c.type_ == "Progressing");
+    if let Some(condition) = progressing
+        .filter(|c| c.status == "False" && c.reason.as_deref() == Some(PROGRESS_DEADLINE_EXCEEDED))
+    {
+        let mut details = details;
+        details["message"] = json!(condition.message);
+        details["last_transition_time"] = json!(condition.last_transition_time);
+        problems.push(Problem {
+            object,
+            issue_tech_id: "deployment-progress-deadline-exceeded",
+            severity: IssueSeverity::High,
+            message: "Deployment rollout exceeded its progress deadline".to_string(),
+            details,
+            immediate: true,
+            since: condition.last_transition_time.as_ref().map(|t| t.0),
+        });
+        return problems;
+    }
+
+    // Available turns False only once more replicas than maxUnavailable are missing
+    let unavailable_since = status
+        .conditions
+        .iter()
+        .flatten()
+        .find(|c| c.type_ == "Available" && c.status == "False")
+        .and_then(|c| c.last_transition_time.as_ref())
+        .map(|t| t.0);
+    let unavailable = status.unavailable_replicas.unwrap_or(0);
+    if unavailable > 0 {
+        problems.push(Problem {
+            object,
+            issue_tech_id: "deployment-unavailable-replicas",
+            severity: IssueSeverity::Medium,
+            message: format!("Deployment has {} unavailable replicas", unavailable),
+            details,
+            immediate: false,
+            since: unavailable_since,
+        });
+    }
+
+    problems
+}
+
+fn statefulset_problems(statefulset: &StatefulSet) -> Vec<Problem> {
+    let mut problems = vec![];
+    let (object, status) = match (
+        reference(
+            "StatefulSet",
+            statefulset.metadata.name.as_deref(),
+            statefulset.metadata.namespace.as_deref(),
+        ),
+        statefulset.status.as_ref(),
+    ) {
+        (Some(o), Some(s)) => (o, s),
+        _ => return problems,
+    };
+
+    let update_strategy = statefulset
+        .spec
+        .as_ref()
+        .and_then(|s| s.update_strategy.as_ref());
+    // OnDelete rollouts wait for pods to be deleted by hand
+    let strategy = update_strategy
+        .and_then(|s| s.type_.as_deref())
+        .unwrap_or("RollingUpdate");
+    // Partitioned rollouts only update the replicas with an ordinal at or above the partition
+    let partition = update_strategy
+        .and_then(|s| s.rolling_update.as_ref())
+        .and_then(|r| r.partition)
+        .filter(|_| strategy == "RollingUpdate")
+        .unwrap_or(0);
+    let updated = status.updated_replicas.unwrap_or(0);
+    let partition_updated = partition > 0 && updated >= (status.replicas - partition).max(0);
+
+    if status.current_revision.is_some()
+        && status.current_revision != status.update_revision
+        && !partition_updated
+    {
+        problems.push(Problem {
+            object,
+            issue_tech_id: "statefulset-rollout-stuck",
+            severity: if strategy == "OnDelete" {
+                IssueSeverity::Low
+            } else {
+                IssueSeverity::Medium
+            },
+            message: format!(
+                "StatefulSet rollout is not complete, {} of {} replicas updated",
+                updated, status.replicas
+            ),
+            details: json!({
+                "current_revision": status.current_revision,
+                "update_revision": status.update_revision,
+                "update_strategy": strategy,
+                "partition": partition,
+                "replicas": status.replicas,
+                "updated_replicas": status.updated_replicas,
+                "ready_replicas": status.ready_replicas,
+            }),
+            immediate: false,
+            since: None,
+        });
+    }
+
+    problems
+}
+
+fn daemonset_problems(daemonset: &DaemonSet) -> Vec<Problem> {
+    let mut problems = vec![];
+    let (object, status) = match (
+        reference(
+            "DaemonSet",
+            daemonset.metadata.name.as_deref(),
+            daemonset.metadata.namespace.as_deref(),
+        ),
+        daemonset.status.as_ref(),
+    ) {
+        (Some(o), Some(s)) => (o, s),
+        _ => return problems,
+    };
+    let details = json!({
+        "desired_number_scheduled": status.desired_number_scheduled,
+        "current_number_scheduled": status.current_number_scheduled,
+        "updated_number_scheduled": status.updated_number_scheduled,
+        "number_ready": status.number_ready,
+        "number_unavailable": status.number_unavailable,
+        "number_misscheduled": status.number_misscheduled,
+    });
+
+    if status.number_misscheduled > 0 {
+        problems.push(Problem {
+            object: object.clone(),
+            issue_tech_id: "daemonset-misscheduled",
+            severity: IssueSeverity::Medium,
+            message: format!(
+                "DaemonSet runs {} pods on nodes they should not run on",
+                status.number_misscheduled
+            ),
+            details: details.clone(),
+            immediate: false,
+            since: None,
+        });
+    }
+
+    let unavailable = status.number_unavailable.unwrap_or(0);
+    if unavailable > 0 {
+        problems.push(Problem {
+            object,
+            issue_tech_id: "daemonset-unavailable-pods",
+            severity: IssueSeverity::Medium,
+            message: format!(
+                "DaemonSet has {} unavailable pods out of {}",
+                unavailable, status.desired_number_scheduled
+            ),
+            details,
+            immediate: false,
+            since: None,
+        });
+    }
+
+    problems
+}
+
+// Report rollouts failing or not completing for longer than a threshold
+pub struct RolloutsAnalyzer {
+    threshold: Duration,
+    // First time each problem was observed, for those no condition tracks how long they last
+    first_seen: HashMap<(ObjectReference, &'static str), DateTime<Utc>>,
+}
+
+impl RolloutsAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            threshold: Duration::seconds(config::env_or("ROLLOUT_STUCK_THRESHOLD", 900)),
+            first_seen: HashMap::new(),
+        }
+    }
+}
+
+impl Analyzer for RolloutsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/rollouts"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["Deployment", "StatefulSet", "DaemonSet"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let now = Utc::now();
+        let mut issues = vec![];
+        let mut first_seen = HashMap::new();
+
+        let problems = state
+            .deployments
+            .iter()
+            .flat_map(deployment_problems)
+            .chain(state.statefulsets.iter().flat_map(statefulset_problems))
+            .chain(state.daemonsets.iter().flat_map(daemonset_problems));
+
+        for problem in problems {
+            let seen = match problem.since {
+                Some(since) => since,
+                None => {
+                    let key = (problem.object.clone(), problem.issue_tech_id);
+                    let seen = *self.first_seen.get(&key).unwrap_or(&now);
+                    first_seen.insert(key, seen);
+                    seen
+                }
+            };
+
+            if !problem.immediate && now - seen < self.threshold {
+                continue;
+            }
+
+            let mut details = problem.details;
+            details["since"] = json!(seen.to_rfc3339());
+            issues.push(
+                Issue::new(
+                    &problem.object,
+                    IssueCategory::Reliability,
+                    problem.severity,
+                    problem.issue_tech_id,
+                    problem.message,
+                )
+                .with_details(details),
+            );
+        }
+
+        // Completed rollouts are forgotten
+        self.first_seen = first_seen;
+        issues
+    }
+}
@@ -0,0 +1,196 @@
+use k8s_openapi::api::core::v1::{Container, PodSpec};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::state::{self, ClusterState};
+
+const DANGEROUS_CAPABILITIES: [&str; 7] = [
+    "ALL",
+    "SYS_ADMIN",
+    "NET_ADMIN",
+    "NET_RAW",
+    "SYS_PTRACE",
+    "SYS_MODULE",
+    "DAC_READ_SEARCH",
+];
+
+const DANGEROUS_CAPABILITIES_TECH_ID: &str = "dangerous-capabilities";
+
+struct Rule {
+    issue_tech_id: &'static str,
+    severity: IssueSeverity,
+    message: &'static str,
+    // Whether the container breaks the rule
+    check: fn(&PodSpec, &Container) -> bool,
+}
+
+const RULES: [Rule; 6] = [
+    Rule {
+        issue_tech_id: "privileged-container",
+        severity: IssueSeverity::Critical,
+        message: "Containers running privileged",
+        check: |_, c| is_privileged(c),
+    },
+    Rule {
+        issue_tech_id: "privilege-escalation-allowed",
+        severity: IssueSeverity::High,
+        message: "Containers allowing privilege escalation",
+        check: |_, c| allows_privilege_escalation(c),
+    },
+    Rule {
+        issue_tech_id: "running-as-root",
+        severity: IssueSeverity::High,
+        message: "Containers allowed to run as root",
+        check: |spec, c| may_run_as_root(spec, c),
+    },
+    Rule {
+        issue_tech_id: "writable-root-filesystem",
+        severity: IssueSeverity::Medium,
+        message: "Containers without a read only root filesystem",
+        check: |_, c| {
+            c.security_context
+                .as_ref()
+                .and_then(|s| s.read_only_root_filesystem)
+                != Some(true)
+        },
+    },
+    Rule {
+        issue_tech_id: DANGEROUS_CAPABILITIES_TECH_ID,
+        severity: IssueSeverity::High,
+        message: "Containers adding dangerous capabilities",
+        check: |_, c| !dangerous_capabilities(c).is_empty(),
+    },
+    Rule {
+        issue_tech_id: "missing-seccomp-profile",
+        severity: IssueSeverity::Medium,
+        message: "Containers without a seccomp profile",
+        check: |spec, c| {
+            !matches!(
+                seccomp_profile(spec, c),
+                Some("RuntimeDefault" | "Localhost")
+            )
+        },
+    },
+];
+
+pub(super) fn is_privileged(container: &Container) -> bool {
+    container
+        .security_context
+        .as_ref()
+        .and_then(|s| s.privileged)
+        .unwrap_or(false)
+}
+
+pub(super) fn allows_privilege_escalation(container: &Container) -> bool {
+    container
+        .security_context
+        .as_ref()
+        .and_then(|s| s.allow_privilege_escalation)
+        != Some(false)
+}
+
+// Container settings take precedence over pod ones
+pub(super) fn may_run_as_root(spec: &PodSpec, container: &Container) -> bool {
+    let container_context = container.security_context.as_ref();
+    let pod_context = spec.security_context.as_ref();
+
+    let run_as_user = container_context
+        .and_then(|s| s.run_as_user)
+        .or_else(|| pod_context.and_then(|s| s.run_as_user));
+    if let Some(uid) = run_as_user {
+        return uid == 0;
+    }
+
+    let run_as_non_root = container_context
+        .and_then(|s| s.run_as_non_root)
+        .or_else(|| pod_context.and_then(|s| s.run_as_non_root));
+    run_as_non_root != Some(true)
+}
+
+pub(super) fn seccomp_profile<'a>(spec: &'a PodSpec, container: &'a Container) -> Option<&'a str> {
+    container
+        .security_context
+        .as_ref()
+        .and_then(|s| s.seccomp_profile.as_ref())
+        .or_else(|| {
+            spec.security_context
+                .as_ref()
+                .and_then(|s| s.seccomp_profile.as_ref())
+        })
+        .map(|p| p.type_.as_str())
+}
+
+// Capabilities may be written with the CAP_ prefix, which the runtime ignores
+fn capability_name(capability: &str) -> &str {
+    capability.trim_start_matches("CAP_")
+}
+
+pub(super) fn added_capabilities(container: &Container) -> Vec<&str> {
+    container
+        .security_context
+        .as_ref()
+        .and_then(|s| s.capabilities.as_ref())
+        .and_then(|c| c.add.as_ref())
+        .map(|add| add.iter().map(|c| capability_name(c)).collect())
+        .unwrap_or_default()
+}
+
+fn dangerous_capabilities(container: &Container) -> Vec<&str> {
+    added_capabilities(container)
+        .into_iter()
+        .filter(|c| DANGEROUS_CAPABILITIES.contains(c))
+        .collect()
+}
+
+// Report pod security context weaknesses, one issue per workload and rule
+pub struct SecurityContextAnalyzer {}
+
+impl Analyzer for SecurityContextAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/security-context"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        state::WORKLOAD_KINDS.to_vec()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        for workload in state.workloads() {
+            for rule in RULES.iter() {
+                let containers: Vec<&Container> = workload
+                    .containers()
+                    .filter(|c| (rule.check)(workload.spec, c))
+                    .collect();
+                if containers.is_empty() {
+                    continue;
+                }
+
+                let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+                let mut details = json!({ "containers": names });
+                if rule.issue_tech_id == DANGEROUS_CAPABILITIES_TECH_ID {
+                    let capabilities: Vec<&str> = containers
+                        .iter()
+                        .flat_map(|c| dangerous_capabilities(c))
+                        .collect();
+                    details["capabilities"] = json!(capabilities);
+                }
+
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Security,
+                        rule.severity,
+                        rule.issue_tech_id,
+                        format!("{}: {}", rule.message, names.join(", ")),
+                    )
+                    .with_details(details),
+                );
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,254 @@
+use k8s_openapi::api::core::v1::ServiceAccount;
+use serde_json::json;
+
+use super::rbac::is_bootstrapped;
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{self, ClusterState, Workload};
+
+const DEFAULT_SERVICE_ACCOUNT: &str = "default";
+const LEGACY_TOKEN_TYPE: &str = "kubernetes.io/service-account-token";
+const SERVICE_ACCOUNT_NAME_ANNOTATION: &str = "kubernetes.io/service-account.name";
+const ALL_SERVICE_ACCOUNTS_GROUP: &str = "system:serviceaccounts";
+const AUTHENTICATED_GROUP: &str = "system:authenticated";
+
+fn service_account_name<'a>(workload: &Workload<'a>) -> &'a str {
+    #[allow(deprecated)]
+    workload
+        .spec
+        .service_account_name
+        .as_deref()
+        .or(workload.spec.service_account.as_deref())
+        .filter(|n| !n.is_empty())
+        .unwrap_or(DEFAULT_SERVICE_ACCOUNT)
+}
+
+// Pod setting takes precedence over the service account one
+fn automounts_token(workload: &Workload, account: Option<&ServiceAccount>) -> bool {
+    workload
+        .spec
+        .automount_service_account_token
+        .or_else(|| account.and_then(|a| a.automount_service_account_token))
+        .unwrap_or(true)
+}
+
+// Whether any binding grants permissions to the service account, besides the built-in ones every cluster
+// grants to all authenticated users and service accounts
+fn is_bound(state: &ClusterState, namespace: &str, name: &str) -> bool {
+    let namespace_group = format!("{}:{}", ALL_SERVICE_ACCOUNTS_GROUP, namespace);
+    let subjects = state
+        .role_bindings
+        .iter()
+        .filter(|b| !is_bootstrapped(&b.metadata))
+        .filter_map(|b| b.subjects.as_ref())
+        .chain(
+            state
+                .cluster_role_bindings
+                .iter()
+                .filter(|b| !is_bootstrapped(&b.metadata))
+                .filter_map(|b| b.subjects.as_ref()),
+        )
+        .flatten();
+
+    for subject in subjects {
+        let matches = match subject.kind.as_str() {
+            "ServiceAccount" => {
+                subject.name == name && subject.namespace.as_deref() == Some(namespace)
+            }
+            "Group" => {
+                subject.name == ALL_SERVICE_ACCOUNTS_GROUP
+                    || subject.name == AUTHENTICATED_GROUP
+                    || subject.name == namespace_group
+            }
+            _ => false,
+        };
+        if matches {
+            return true;
+        }
+    }
+    false
+}
+
+// Report workloads relying on the default service account, needless API tokens and legacy tokens
+pub struct ServiceAccountsAnalyzer {}
+
+impl Analyzer for ServiceAccountsAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/service-accounts"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        [
+            state::WORKLOAD_KINDS.as_slice(),
+            &[
+                "ServiceAccount",
+                "Secret",
+                "RoleBinding",
+                "ClusterRoleBinding",
+            ],
+        ]
+        .concat()
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        let workloads = state.workloads();
+        for workload in workloads.iter() {
+            let namespace = workload.object.namespace.as_str();
+            let name = service_account_name(workload);
+            let account = state.service_accounts.iter().find(|a| {
+                a.metadata.name.as_deref() == Some(name)
+                    && a.metadata.namespace.as_deref() == Some(namespace)
+            });
+            let automounted = automounts_token(workload, account);
+
+            if name == DEFAULT_SERVICE_ACCOUNT {
+                issues.push(Issue::new(
+                    &workload.object,
+                    IssueCategory::Security,
+                    IssueSeverity::Medium,
+                    "default-service-account",
+                    "Pods run with the default service account, shared by every workload of the namespace"
+                        .to_string(),
+                ));
+            }
+
+            if automounted && !is_bound(state, namespace, name) {
+                issues.push(
+                    Issue::new(
+                        &workload.object,
+                        IssueCategory::Security,
+                        IssueSeverity::Low,
+                        "unneeded-token-automount",
+                        "Pods mount an API token although their service account has no permission"
+                            .to_string(),
+                    )
+                    .with_linked_object(ObjectReference::new(
+                        "ServiceAccount",
+                        name,
+                        namespace,
+                    )),
+                );
+            }
+        }
+
+        // Tokens are reported whether a workload uses their service account or not
+        for token in state
+            .secrets
+            .iter()
+            .filter(|s| s.type_.as_deref() == Some(LEGACY_TOKEN_TYPE))
+        {
+            let (token_name, namespace, name) = match (
+                token.metadata.name.as_deref(),
+                token.metadata.namespace.as_deref(),
+                token
+                    .metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(SERVICE_ACCOUNT_NAME_ANNOTATION)),
+            ) {
+                (Some(t), Some(ns), Some(n)) => (t, ns, n.as_str()),
+                _ => continue,
+            };
+            let users: Vec<&ObjectReference> = workloads
+                .iter()
+                .filter(|w| w.object.namespace == namespace && service_account_name(w) == name)
+                .map(|w| &w.object)
+                .collect();
+            issues.push(
+                Issue::new(
+                    &ObjectReference::new("Secret", token_name, namespace),
+                    IssueCategory::Security,
+                    IssueSeverity::Medium,
+                    "legacy-service-account-token",
+                    format!(
+                        "Secret holds a long-lived token of service account {}, used by {} workloads",
+                        name,
+                        users.len()
+                    ),
+                )
+                .with_details(json!({
+                    "service_account": name,
+                    "created_at": token.metadata.creation_timestamp,
+                    "workloads": users,
+                }))
+                .with_linked_object(ObjectReference::new("ServiceAccount", name, namespace)),
+            );
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::apps::v1::Deployment;
+    use k8s_openapi::api::rbac::v1::ClusterRoleBinding;
+
+    use super::*;
+
+    fn deployment(service_account: &str) -> Deployment {
+        serde_json::from_value(json!({
+            "metadata": { "name": "web", "namespace": "shop" },
+            "spec": {
+                "selector": {},
+                "template": { "spec": {
+                    "serviceAccountName": service_account,
+                    "containers": [{ "name": "web" }],
+                } },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn group_binding(name: &str, group: &str, bootstrapped: bool) -> ClusterRoleBinding {
+        let labels = if bootstrapped {
+            json!({ "kubernetes.io/bootstrapping": "rbac-defaults" })
+        } else {
+            json!({})
+        };
+        serde_json::from_value(json!({
+            "metadata": { "name": name, "labels": labels },
+            "roleRef": { "apiGroup": "rbac.authorization.k8s.io", "kind": "ClusterRole", "name": name },
+            "subjects": [{ "kind": "Group", "name": group }],
+        }))
+        .unwrap()
+    }
+
+    fn automount_issues(state: &ClusterState) -> usize {
+        ServiceAccountsAnalyzer {}
+            .analyze(state)
+            .iter()
+            .filter(|i| i.issue_tech_id == "unneeded-token-automount")
+            .count()
+    }
+
+    #[test]
+    fn ignores_built_in_bindings() {
+        let state = ClusterState {
+            deployments: vec![deployment("web")],
+            cluster_role_bindings: vec![
+                group_binding("system:basic-user", AUTHENTICATED_GROUP, true),
+                group_binding("system:discovery", AUTHENTICATED_GROUP, true),
+                group_binding(
+                    "system:service-account-issuer-discovery",
+                    ALL_SERVICE_ACCOUNTS_GROUP,
+                    true,
+                ),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(automount_issues(&state), 1);
+    }
+
+    #[test]
+    fn counts_group_bindings() {
+        let state = ClusterState {
+            deployments: vec![deployment("web")],
+            cluster_role_bindings: vec![group_binding("viewers", AUTHENTICATED_GROUP, false)],
+            ..Default::default()
+        };
+        assert_eq!(automount_issues(&state), 0);
+    }
+}
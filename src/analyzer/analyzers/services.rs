@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use k8s_openapi::api::core::v1::{Endpoints, Service};
+use k8s_openapi::api::networking::v1::{Ingress, IngressBackend};
+use serde_json::json;
+
+use super::Analyzer;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::selectors;
+use crate::state::ClusterState;
+
+fn ready_addresses(endpoints: Option<&Endpoints>) -> usize {
+    endpoints
+        .and_then(|e| e.subsets.as_ref())
+        .map(|subsets| {
+            subsets
+                .iter()
+                .map(|s| s.addresses.as_ref().map(Vec::len).unwrap_or(0))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn service_issues(state: &ClusterState, service: &Service, object: &ObjectReference) -> Vec<Issue> {
+    let spec = match service.spec.as_ref() {
+        Some(s) => s,
+        None => return vec![],
+    };
+    // ExternalName services have no endpoints
+    if spec.type_.as_deref() == Some("ExternalName") {
+        return vec![];
+    }
+
+    let selector = spec.selector.as_ref().filter(|s| !s.is_empty());
+    if let Some(selector) = selector {
+        let selected = state
+            .pods
+            .iter()
+            .filter(|p| p.metadata.namespace.as_deref() == Some(object.namespace.as_str()))
+            .filter(|p| selectors::matches_labels(selector, p.metadata.labels.as_ref()))
+            .count();
+        if selected == 0 {
+            return vec![Issue::new(
+                object,
+                IssueCategory::Reliability,
+                IssueSeverity::High,
+                "service-selector-no-pods",
+                "Service selector does not match any pod".to_string(),
+            )
+            .with_details(json!({ "selector": selector }))];
+        }
+    }
+
+    let endpoints = state.endpoints.iter().find(|e| {
+        e.metadata.name.as_deref() == Some(object.object_name.as_str())
+            && e.metadata.namespace.as_deref() == Some(object.namespace.as_str())
+    });
+    if ready_addresses(endpoints) > 0 {
+        return vec![];
+    }
+
+    let not_ready: usize = endpoints
+        .and_then(|e| e.subsets.as_ref())
+        .map(|subsets| {
+            subsets
+                .iter()
+                .map(|s| s.not_ready_addresses.as_ref().map(Vec::len).unwrap_or(0))
+                .sum()
+        })
+        .unwrap_or(0);
+    vec![Issue::new(
+        object,
+        IssueCategory::Reliability,
+        IssueSeverity::High,
+        "service-no-ready-endpoints",
+        "Service has no ready endpoint, requests to it fail".to_string(),
+    )
+    .with_details(json!({ "not_ready_endpoints": not_ready }))]
+}
+
+fn backend_issue(
+    state: &ClusterState,
+    object: &ObjectReference,
+    backend: &IngressBackend,
+) -> Option<Issue> {
+    let target = backend.service.as_ref()?;
+    let linked_object = ObjectReference::new("Service", &target.name, &object.namespace);
+
+    let service = state.services.iter().find(|s| {
+        s.metadata.name.as_deref() == Some(target.name.as_str())
+            && s.metadata.namespace.as_deref() == Some(object.namespace.as_str())
+    });
+    let service = match service {
+        Some(s) => s,
+        None => {
+            return Some(
+                Issue::new(
+                    object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "ingress-missing-service",
+                    format!("Ingress routes to missing service {}", target.name),
+                )
+                .with_linked_object(linked_object),
+            )
+        }
+    };
+
+    let port = target.port.as_ref()?;
+    let ports = service.spec.as_ref().and_then(|s| s.ports.as_ref());
+    let exists = ports.iter().copied().flatten().any(|p| match port.number {
+        Some(number) => p.port == number,
+        None => p.name.is_some() && p.name == port.name,
+    });
+    if exists {
+        return None;
+    }
+
+    let port_name = port
+        .number
+        .map(|n| n.to_string())
+        .or_else(|| port.name.clone())
+        .unwrap_or_default();
+    Some(
+        Issue::new(
+            object,
+            IssueCategory::Reliability,
+            IssueSeverity::High,
+            "ingress-missing-service-port",
+            format!(
+                "Ingress routes to port {} not exposed by service {}",
+                port_name, target.name
+            ),
+        )
+        .with_details(json!({ "port": port_name }))
+        .with_linked_object(linked_object),
+    )
+}
+
+fn ingress_issues(state: &ClusterState, ingress: &Ingress, object: &ObjectReference) -> Vec<Issue> {
+    let spec = match ingress.spec.as_ref() {
+        Some(s) => s,
+        None => return vec![],
+    };
+    let mut issues = vec![];
+
+    let backends = spec.default_backend.iter().chain(
+        spec.rules
+            .iter()
+            .flatten()
+            .filter_map(|r| r.http.as_ref())
+            .flat_map(|h| h.paths.iter().map(|p| &p.backend)),
+    );
+    // One issue per broken target, several paths may route to it
+    let mut reported = BTreeSet::new();
+    for backend in backends {
+        if let Some(issue) = backend_issue(state, object, backend) {
+            if reported.insert((issue.issue_tech_id.clone(), issue.linked_object.clone())) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    let secrets: BTreeSet<&str> = spec
+        .tls
+        .iter()
+        .flatten()
+        .filter_map(|t| t.secret_name.as_deref())
+        .collect();
+    for secret in secrets {
+        let exists = state.secrets.iter().any(|s| {
+            s.metadata.name.as_deref() == Some(secret)
+                && s.metadata.namespace.as_deref() == Some(object.namespace.as_str())
+        });
+        if !exists {
+            issues.push(
+                Issue::new(
+                    object,
+                    IssueCategory::Reliability,
+                    IssueSeverity::High,
+                    "ingress-missing-tls-secret",
+                    format!("Ingress TLS references missing secret {}", secret),
+                )
+                .with_linked_object(ObjectReference::new(
+                    "Secret",
+                    secret,
+                    &object.namespace,
+                )),
+            );
+        }
+    }
+
+    issues
+}
+
+const INGRESS_CLASS_ANNOTATION: &str = "kubernetes.io/ingress.class";
+
+// Class set by the field, or by the annotation it replaced
+fn ingress_class(ingress: &Ingress) -> Option<&str> {
+    ingress
+        .spec
+        .as_ref()
+        .and_then(|s| s.ingress_class_name.as_deref())
+        .or_else(|| {
+            ingress
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(INGRESS_CLASS_ANNOTATION))
+                .map(String::as_str)
+        })
+}
+
+// Report Services and Ingresses routing traffic nowhere
+pub struct ServicesAnalyzer {}
+
+impl Analyzer for ServicesAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/services"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["Service", "Endpoints", "Ingress", "Pod", "Secret"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues = vec![];
+
+        for service in state.services.iter() {
+            if let (Some(name), Some(namespace)) = (
+                service.metadata.name.as_deref(),
+                service.metadata.namespace.as_deref(),
+            ) {
+                let object = ObjectReference::new("Service", name, namespace);
+                issues.extend(service_issues(state, service, &object));
+            }
+        }
+
+        // Ingresses serving every host and path, within each ingress class
+        let mut routes: BTreeMap<(Option<&str>, &str, &str, &str), Vec<ObjectReference>> =
+            BTreeMap::new();
+        for ingress in state.ingresses.iter() {
+            let object = match (
+                ingress.metadata.name.as_deref(),
+                ingress.metadata.namespace.as_deref(),
+            ) {
+                (Some(name), Some(namespace)) => ObjectReference::new("Ingress", name, namespace),
+                _ => continue,
+            };
+            issues.extend(ingress_issues(state, ingress, &object));
+
+            let spec = match ingress.spec.as_ref() {
+                Some(s) => s,
+                None => continue,
+            };
+            for rule in spec.rules.iter().flatten() {
+                let host = rule.host.as_deref().unwrap_or("*");
+                for path in rule.http.iter().flat_map(|h| h.paths.iter()) {
+                    let ingresses = routes
+                        .entry((
+                            ingress_class(ingress),
+                            host,
+                            path.path.as_deref().unwrap_or("/"),
+                            path.path_type.as_str(),
+                        ))
+                        .or_default();
+                    if !ingresses.contains(&object) {
+                        ingresses.push(object.clone());
+                    }
+                }
+            }
+        }
+
+        // Each pair is reported once, on the first ingress and linked to the other
+        let mut reported = BTreeSet::new();
+        for ((_, host, path, _), ingresses) in routes.iter() {
+            for (i, object) in ingresses.iter().enumerate() {
+                for other in ingresses.iter().skip(i + 1) {
+                    let (first, second) = if object < other {
+                        (object, other)
+                    } else {
+                        (other, object)
+                    };
+                    if !reported.insert((first, second)) {
+                        continue;
+                    }
+                    issues.push(
+                        Issue::new(
+                            first,
+                            IssueCategory::Configuration,
+                            IssueSeverity::Medium,
+                            "ingress-duplicate-rule",
+                            format!(
+                                "Ingress serves {}{} also served by ingress {}/{}",
+                                host, path, second.namespace, second.object_name
+                            ),
+                        )
+                        .with_details(json!({ "host": host, "path": path }))
+                        .with_linked_object(second.clone()),
+                    );
+                }
+            }
+        }
+
+        issues
+    }
+}
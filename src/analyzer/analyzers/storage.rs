@@ -0,0 +1,317 @@
+use std::collections::BTreeSet;
+
+use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::storage::v1::StorageClass;
+use serde_json::json;
+
+use super::Analyzer;
+use crate::config;
+use crate::issues::{Issue, IssueCategory, IssueSeverity, ObjectReference};
+use crate::state::{ClusterState, VolumeUsage};
+
+const DEFAULT_CLASS_ANNOTATIONS: [&str; 2] = [
+    "storageclass.kubernetes.io/is-default-class",
+    "storageclass.beta.kubernetes.io/is-default-class",
+];
+const WAIT_FOR_FIRST_CONSUMER: &str = "WaitForFirstConsumer";
+const READ_WRITE_ONCE: &str = "ReadWriteOnce";
+const PARALLEL: &str = "Parallel";
+
+fn is_default_class(class: &StorageClass) -> bool {
+    class
+        .metadata
+        .annotations
+        .as_ref()
+        .map(|a| {
+            DEFAULT_CLASS_ANNOTATIONS
+                .iter()
+                .any(|k| a.get(*k).map(String::as_str) == Some("true"))
+        })
+        .unwrap_or(false)
+}
+
+fn claim_reference(claim: &PersistentVolumeClaim) -> Option<ObjectReference> {
+    Some(ObjectReference::new(
+        "PersistentVolumeClaim",
+        claim.metadata.name.as_deref()?,
+        claim.metadata.namespace.as_deref()?,
+    ))
+}
+
+fn is_read_write_once(claim: &PersistentVolumeClaim) -> bool {
+    claim
+        .spec
+        .as_ref()
+        .and_then(|s| s.access_modes.as_ref())
+        .map(|m| m.iter().any(|m| m == READ_WRITE_ONCE))
+        .unwrap_or(false)
+}
+
+// Report unbound or lost claims, claims without usable storage class, and volumes running out of space
+pub struct StorageAnalyzer {
+    usage_warning_ratio: f64,
+    usage_critical_ratio: f64,
+}
+
+impl StorageAnalyzer {
+    pub fn from_env() -> Self {
+        Self {
+            usage_warning_ratio: config::env_or("VOLUME_USAGE_WARNING_RATIO", 0.85),
+            usage_critical_ratio: config::env_or("VOLUME_USAGE_CRITICAL_RATIO", 0.95),
+        }
+    }
+
+    fn claim_issues(
+        state: &ClusterState,
+        claim: &PersistentVolumeClaim,
+        object: &ObjectReference,
+    ) -> Vec<Issue> {
+        let mut issues = vec![];
+        let phase = claim.status.as_ref().and_then(|s| s.phase.as_deref());
+        let class_name = claim
+            .spec
+            .as_ref()
+            .and_then(|s| s.storage_class_name.as_deref());
+
+        // An empty class name explicitly asks for a pre-provisioned volume
+        let class = match class_name {
+            Some("") => None,
+            Some(name) => {
+                let class = state
+                    .storage_classes
+                    .iter()
+                    .find(|c| c.metadata.name.as_deref() == Some(name));
+                if class.is_none() {
+                    issues.push(
+                        Issue::new(
+                            object,
+                            IssueCategory::Reliability,
+                            IssueSeverity::High,
+                            "pvc-missing-storage-class",
+                            format!("PersistentVolumeClaim uses missing storage class {}", name),
+                        )
+                        .with_linked_object(ObjectReference::cluster_scoped("StorageClass", name)),
+                    );
+                }
+                class
+            }
+            // The cluster missing a default class is reported once
+            None => state.storage_classes.iter().find(|c| is_default_class(c)),
+        };
+
+        match phase {
+            Some("Lost") => issues.push(Issue::new(
+                object,
+                IssueCategory::Reliability,
+                IssueSeverity::Critical,
+                "pvc-lost",
+                "PersistentVolumeClaim lost its bound volume".to_string(),
+            )),
+            Some("Pending") => {
+                // Such claims are bound once a pod using them is scheduled
+                let waits_for_consumer = class.and_then(|c| c.volume_binding_mode.as_deref())
+                    == Some(WAIT_FOR_FIRST_CONSUMER);
+                if !waits_for_consumer {
+                    issues.push(
+                        Issue::new(
+                            object,
+                            IssueCategory::Reliability,
+                            IssueSeverity::High,
+                            "pvc-pending",
+                            "PersistentVolumeClaim is not bound to any volume".to_string(),
+                        )
+                        .with_details(json!({ "storage_class": class_name })),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        issues
+    }
+
+    // Claims without class are left unbound without a default class
+    fn default_class_issue(state: &ClusterState) -> Option<Issue> {
+        if state.storage_classes.iter().any(is_default_class) {
+            return None;
+        }
+        let pending_claims: Vec<ObjectReference> = state
+            .persistent_volume_claims
+            .iter()
+            .filter(|c| {
+                c.spec
+                    .as_ref()
+                    .and_then(|s| s.storage_class_name.as_ref())
+                    .is_none()
+                    && c.status.as_ref().and_then(|s| s.phase.as_deref()) == Some("Pending")
+            })
+            .filter_map(claim_reference)
+            .collect();
+        let severity = if pending_claims.is_empty() {
+            IssueSeverity::Low
+        } else {
+            IssueSeverity::High
+        };
+        Some(
+            Issue::new(
+                &ObjectReference::cluster(),
+                IssueCategory::Reliability,
+                severity,
+                "no-default-storage-class",
+                "Cluster has no default storage class, claims without storage class can't be provisioned"
+                    .to_string(),
+            )
+            .with_details(json!({ "pending_claims": pending_claims })),
+        )
+    }
+
+    fn usage_issue(&self, state: &ClusterState, usage: &VolumeUsage) -> Option<Issue> {
+        if usage.capacity_bytes <= 0.0 {
+            return None;
+        }
+        let ratio = usage.used_bytes / usage.capacity_bytes;
+        let severity = if ratio >= self.usage_critical_ratio {
+            IssueSeverity::High
+        } else if ratio >= self.usage_warning_ratio {
+            IssueSeverity::Medium
+        } else {
+            return None;
+        };
+
+        let claim = state.persistent_volume_claims.iter().find(|c| {
+            c.metadata.name.as_deref() == Some(usage.claim_name.as_str())
+                && c.metadata.namespace.as_deref() == Some(usage.namespace.as_str())
+        });
+        let expandable = claim
+            .and_then(|c| c.spec.as_ref())
+            .and_then(|s| s.storage_class_name.as_deref())
+            .and_then(|name| {
+                state
+                    .storage_classes
+                    .iter()
+                    .find(|c| c.metadata.name.as_deref() == Some(name))
+            })
+            .and_then(|c| c.allow_volume_expansion)
+            .unwrap_or(false);
+
+        Some(
+            Issue::new(
+                &ObjectReference::new("PersistentVolumeClaim", &usage.claim_name, &usage.namespace),
+                IssueCategory::Reliability,
+                severity,
+                "volume-nearly-full",
+                format!("Volume is {:.0}% full", ratio * 100.0),
+            )
+            .with_details(json!({
+                "used_bytes": usage.used_bytes,
+                "capacity_bytes": usage.capacity_bytes,
+                "expandable": expandable,
+            })),
+        )
+    }
+}
+
+impl Analyzer for StorageAnalyzer {
+    fn name(&self) -> &'static str {
+        "analyzer/storage"
+    }
+
+    fn requires(&self) -> Vec<&'static str> {
+        vec!["PersistentVolumeClaim", "StatefulSet", "StorageClass"]
+    }
+
+    fn analyze(&mut self, state: &ClusterState) -> Vec<Issue> {
+        let mut issues: Vec<Issue> = Self::default_class_issue(state).into_iter().collect();
+
+        for claim in state.persistent_volume_claims.iter() {
+            if let Some(object) = claim_reference(claim) {
+                issues.extend(Self::claim_issues(state, claim, &object));
+            }
+        }
+
+        // Volumes mounted by several pods are reported once
+        let mut seen = BTreeSet::new();
+        for usage in state.volume_usages.iter() {
+            if seen.insert((&usage.namespace, &usage.claim_name)) {
+                issues.extend(self.usage_issue(state, usage));
+            }
+        }
+
+        // Replicas sharing a ReadWriteOnce claim can't run on different nodes: Parallel replicas
+        // start together and fail at once, OrderedReady ones stall scaling and rollouts on the
+        // first replica placed on another node
+        for statefulset in state.statefulsets.iter() {
+            let (name, namespace, spec) = match (
+                statefulset.metadata.name.as_deref(),
+                statefulset.metadata.namespace.as_deref(),
+                statefulset.spec.as_ref(),
+            ) {
+                (Some(n), Some(ns), Some(s)) => (n, ns, s),
+                _ => continue,
+            };
+            if spec.replicas.unwrap_or(1) < 2 {
+                continue;
+            }
+
+            let volumes = spec.template.spec.as_ref().and_then(|s| s.volumes.as_ref());
+            let shared_claims: Vec<&str> = volumes
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.persistent_volume_claim.as_ref())
+                .map(|c| c.claim_name.as_str())
+                .filter(|claim_name| {
+                    state.persistent_volume_claims.iter().any(|c| {
+                        c.metadata.name.as_deref() == Some(*claim_name)
+                            && c.metadata.namespace.as_deref() == Some(namespace)
+                            && is_read_write_once(c)
+                    })
+                })
+                .collect();
+            if shared_claims.is_empty() {
+                continue;
+            }
+
+            let policy = spec
+                .pod_management_policy
+                .as_deref()
+                .unwrap_or("OrderedReady");
+            let (severity, consequence) = if policy == PARALLEL {
+                (
+                    IssueSeverity::High,
+                    "replicas started together fail to mount it",
+                )
+            } else {
+                (
+                    IssueSeverity::Medium,
+                    "scaling and rollouts stall on the first replica placed elsewhere",
+                )
+            };
+            let object = ObjectReference::new("StatefulSet", name, namespace);
+            for claim_name in shared_claims {
+                issues.push(
+                    Issue::new(
+                        &object,
+                        IssueCategory::Reliability,
+                        severity,
+                        "statefulset-shared-rwo-claim",
+                        format!(
+                            "Replicas share ReadWriteOnce claim {} and can't run on other nodes, {}",
+                            claim_name, consequence
+                        ),
+                    )
+                    .with_details(json!({
+                        "pod_management_policy": policy,
+                        "replicas": spec.replicas,
+                    }))
+                    .with_linked_object(ObjectReference::new(
+                        "PersistentVolumeClaim",
+                        claim_name,
+                        namespace,
+                    )),
+                );
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,21 @@
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Read a setting from environment, falling back to its default when unset or invalid
+pub fn env_or<T>(name: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(name) {
+        Ok(value) => match value.parse::<T>() {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}, {}", name, value, e);
+                default
+            }
+        },
+        Err(_e) => default,
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::runtime::{watcher, WatchStreamExt};
+use log::error;
+
+use crate::issues::ObjectReference;
+
+// Warning event as last received, with the occurrences Kubernetes counted on it
+#[derive(Clone, Debug)]
+pub struct WarningEvent {
+    pub involved_object: ObjectReference,
+    pub reason: String,
+    pub message: String,
+    // Occurrence times with their number. Occurrences counted between two receptions are only
+    // known to be after the previous one, they are dated at it
+    pub occurrences: Vec<(DateTime<Utc>, i32)>,
+    count: i32,
+    last_seen: DateTime<Utc>,
+}
+
+impl WarningEvent {
+    fn from_event(event: &Event) -> Option<Self> {
+        let involved = &event.involved_object;
+        let series = event.series.as_ref();
+        let last_seen = event
+            .last_timestamp
+            .as_ref()
+            .map(|t| t.0)
+            .or_else(|| {
+                series
+                    .and_then(|s| s.last_observed_time.as_ref())
+                    .map(|t| t.0)
+            })
+            .or_else(|| event.event_time.as_ref().map(|t| t.0))
+            .unwrap_or_else(Utc::now);
+        let first_seen = event
+            .first_timestamp
+            .as_ref()
+            .map(|t| t.0)
+            .or_else(|| event.event_time.as_ref().map(|t| t.0))
+            .unwrap_or(last_seen);
+        let count = series
+            .and_then(|s| s.count)
+            .or(event.count)
+            .unwrap_or(1)
+            .max(1);
+
+        Some(Self {
+            involved_object: ObjectReference::new(
+                involved.kind.as_deref()?,
+                involved.name.as_deref()?,
+                involved.namespace.as_deref()?,
+            ),
+            reason: event.reason.clone()?,
+            message: event.message.clone().unwrap_or_default(),
+            occurrences: occurrences(first_seen, last_seen, count),
+            count,
+            last_seen,
+        })
+    }
+
+    // Keep the occurrences of the previous reception, adding the ones counted since
+    fn update(&mut self, previous: WarningEvent) {
+        if self.count < previous.count {
+            // Counting restarted, the event was recreated under the same uid
+            return;
+        }
+        let new_occurrences = occurrences(
+            previous.last_seen,
+            self.last_seen,
+            self.count - previous.count,
+        );
+        self.occurrences = previous.occurrences;
+        self.occurrences.extend(new_occurrences);
+    }
+}
+
+// The last occurrence is dated, the others only known to be after from
+fn occurrences(
+    from: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    count: i32,
+) -> Vec<(DateTime<Utc>, i32)> {
+    match count {
+        0 => vec![],
+        1 => vec![(last_seen, 1)],
+        _ => vec![(from, count - 1), (last_seen, 1)],
+    }
+}
+
+// Watched warning events by uid, shared between the watcher and the events analyzer
+#[derive(Clone)]
+pub struct EventStore {
+    reasons: Arc<Vec<&'static str>>,
+    events: Arc<Mutex<HashMap<String, WarningEvent>>>,
+}
+
+impl EventStore {
+    // Only events with the given reasons are kept
+    pub fn new(reasons: Vec<&'static str>) -> Self {
+        Self {
+            reasons: Arc::new(reasons),
+            events: Arc::default(),
+        }
+    }
+
+    fn record(&self, event: &Event) {
+        let uid = match event.metadata.uid.as_ref() {
+            Some(u) => u.clone(),
+            None => return,
+        };
+        let mut warning = match WarningEvent::from_event(event) {
+            Some(w) if self.reasons.contains(&w.reason.as_str()) => w,
+            _ => return,
+        };
+        if let Ok(mut events) = self.events.lock() {
+            if let Some(previous) = events.remove(&uid) {
+                warning.update(previous);
+            }
+            events.insert(uid, warning);
+        }
+    }
+
+    // Events with their occurrences since the given time, older ones being forgotten
+    pub fn recent(&self, since: DateTime<Utc>) -> Vec<WarningEvent> {
+        match self.events.lock() {
+            Ok(mut events) => {
+                for event in events.values_mut() {
+                    event.occurrences.retain(|(seen, _)| *seen >= since);
+                }
+                events.retain(|_, e| !e.occurrences.is_empty());
+                events.values().cloned().collect()
+            }
+            Err(_) => vec![],
+        }
+    }
+}
+
+// Feed the store with warning events of the whole cluster, until the watch ends
+pub async fn watch(kube_client: kube::Client, store: EventStore) {
+    let api: kube::Api<Event> = kube::Api::all(kube_client);
+    let config = watcher::Config::default().fields("type=Warning");
+    let mut events = watcher(api, config)
+        .default_backoff()
+        .applied_objects()
+        .boxed();
+
+    loop {
+        match events.try_next().await {
+            Ok(Some(event)) => store.record(&event),
+            Ok(None) => break,
+            Err(e) => error!("Unable to watch events: {}", e),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+
+// Mirrors the apiservice issue model, as published on /v1/issues
+
+#[allow(dead_code)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IssueCategory {
+    Security,
+    Reliability,
+    Performance,
+    Configuration,
+    Cost,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectReference {
+    pub object_type: String,
+    pub object_name: String,
+    pub namespace: String,
+}
+
+impl ObjectReference {
+    pub fn new(object_type: &str, object_name: &str, namespace: &str) -> Self {
+        Self {
+            object_type: object_type.to_string(),
+            object_name: object_name.to_string(),
+            namespace: namespace.to_string(),
+        }
+    }
+
+    // Objects outside of any namespace, listed with cluster-wide rights
+    pub fn cluster_scoped(object_type: &str, object_name: &str) -> Self {
+        Self::new(object_type, object_name, "")
+    }
+
+    // Namespaces are the exception, filed under themselves so that their users see their issues
+    pub fn namespace(name: &str) -> Self {
+        Self::new("Namespace", name, name)
+    }
+
+    // The cluster itself, identified by the cluster its issues are published for
+    pub fn cluster() -> Self {
+        Self::new("Cluster", "", "")
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Issue {
+    pub cluster: String,
+    pub namespace: String,
+    pub object_name: String,
+    pub object_type: String,
+    pub category: IssueCategory,
+    pub details: Option<String>,
+    pub severity: IssueSeverity,
+    pub issue_tech_id: String,
+    pub issue_message: String,
+    pub reported_by: Option<String>,
+    pub reported_at: Option<String>,
+    pub linked_object: Option<ObjectReference>,
+}
+
+impl Issue {
+    pub fn new(
+        object: &ObjectReference,
+        category: IssueCategory,
+        severity: IssueSeverity,
+        issue_tech_id: &str,
+        issue_message: String,
+    ) -> Self {
+        Self {
+            cluster: String::new(),
+            namespace: object.namespace.clone(),
+            object_name: object.object_name.clone(),
+            object_type: object.object_type.clone(),
+            category,
+            details: None,
+            severity,
+            issue_tech_id: issue_tech_id.to_string(),
+            issue_message,
+            reported_by: None,
+            reported_at: None,
+            linked_object: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    pub fn with_linked_object(mut self, linked_object: ObjectReference) -> Self {
+        self.linked_object = Some(linked_object);
+        self
+    }
+}
+
+// Issues of the reporters on the cluster which are not in the list are resolved
+#[derive(Serialize)]
+pub struct IssueResolution {
+    pub cluster: String,
+    pub reporters: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct IssueList {
+    pub issues: Vec<Issue>,
+    pub resolution: Option<IssueResolution>,
+}
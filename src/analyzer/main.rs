@@ -1,3 +1,80 @@
-fn main() {
-    println!("Coa");
+use std::env;
+use std::time::Duration;
+
+use k8s_openapi::chrono::Utc;
+use log::{error, info, warn};
+
+mod analyzers;
+mod config;
+mod events;
+mod issues;
+mod publisher;
+mod selectors;
+mod state;
+
+// Apiservice pricing of a GiB of persistent storage
+const PRICING_STORAGE: &str = "storage";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    std_logger::Config::logfmt().init();
+
+    let api_url: String = config::env_or("API_URL", "http://localhost:3000".to_string());
+    let cluster_name: String = config::env_or("CLUSTER_NAME", "unknown".to_string());
+    let interval: u64 = config::env_or("ANALYZE_INTERVAL", 300);
+
+    if env::var("KUBECONFIG").is_err() {
+        eprintln!("KUBECONFIG environment variable not set");
+        std::process::exit(1);
+    }
+
+    let kube_client = kube::Client::try_default().await?;
+    let publisher = publisher::Publisher::new(api_url);
+    let event_store = events::EventStore::new(analyzers::watched_event_reasons());
+    tokio::spawn(events::watch(kube_client.clone(), event_store.clone()));
+    let mut analyzers = analyzers::all(event_store);
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        ticker.tick().await;
+
+        let mut state = state::ClusterState::fetch(&kube_client).await;
+        state.storage_gib_month_price = match publisher.monthly_price(PRICING_STORAGE).await {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Unable to read storage pricing: {}", e);
+                None
+            }
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let mut issues = vec![];
+        let mut reporters = vec![];
+        for analyzer in analyzers.iter_mut() {
+            // Issues no longer reported are resolved, unless the analyzer misses some of the objects it reads
+            if analyzer
+                .requires()
+                .iter()
+                .all(|k| !state.missing.contains(*k))
+            {
+                reporters.push(analyzer.name().to_string());
+            }
+            for mut issue in analyzer.analyze(&state) {
+                issue.cluster = cluster_name.clone();
+                issue.reported_by = Some(analyzer.name().to_string());
+                issue.reported_at = Some(now.clone());
+                issues.push(issue);
+            }
+        }
+
+        let resolution = issues::IssueResolution {
+            cluster: cluster_name.clone(),
+            reporters,
+        };
+
+        info!("Publishing {} issues", issues.len());
+        if let Err(e) = publisher.publish(issues, resolution).await {
+            error!("Unable to publish issues: {}", e);
+        }
+    }
 }
@@ -0,0 +1,75 @@
+use coi::pricing::{PricingPeriod, HOURS_PER_MONTH};
+use hyper::{client::HttpConnector, Body, Client, Method, Request, StatusCode};
+use serde::Deserialize;
+
+use crate::issues::{Issue, IssueList, IssueResolution};
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+// Mirrors the apiservice pricing model
+#[derive(Deserialize)]
+struct Pricing {
+    price: f64,
+    period: PricingPeriod,
+}
+
+// Publish issues to the apiservice, and read the pricing costs are estimated with
+pub struct Publisher {
+    client: Client<HttpConnector>,
+    api_url: String,
+}
+
+impl Publisher {
+    pub fn new(api_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn publish(
+        &self,
+        issues: Vec<Issue>,
+        resolution: IssueResolution,
+    ) -> Result<(), Error> {
+        let body = serde_json::to_vec(&IssueList {
+            issues,
+            resolution: Some(resolution),
+        })?;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}/v1/issues", self.api_url))
+            .header("content-type", "application/json")
+            .body(Body::from(body))?;
+
+        let response = self.client.request(request).await?;
+        if !response.status().is_success() {
+            return Err(format!("API replied with status {}", response.status()).into());
+        }
+        Ok(())
+    }
+
+    // Monthly price of an object type, None when the apiservice defines no pricing for it
+    pub async fn monthly_price(&self, object_type: &str) -> Result<Option<f64>, Error> {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!(
+                "{}/v1/billing/pricing/{}",
+                self.api_url, object_type
+            ))
+            .body(Body::empty())?;
+
+        let response = self.client.request(request).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("API replied with status {}", response.status()).into());
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        let pricing: Pricing = serde_json::from_slice(&body)?;
+        Ok(Some(
+            pricing.price * HOURS_PER_MONTH / pricing.period.hours(),
+        ))
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+
+// Whether labels are selected by a label selector, an empty selector selecting everything
+pub fn matches(selector: &LabelSelector, labels: Option<&BTreeMap<String, String>>) -> bool {
+    let empty = BTreeMap::new();
+    let labels = labels.unwrap_or(&empty);
+
+    let labels_match = selector
+        .match_labels
+        .as_ref()
+        .map(|s| matches_labels(s, Some(labels)))
+        .unwrap_or(true);
+
+    let expressions_match = selector.match_expressions.iter().flatten().all(|e| {
+        let value = labels.get(&e.key);
+        let in_values = value
+            .map(|v| e.values.iter().flatten().any(|candidate| candidate == v))
+            .unwrap_or(false);
+        match e.operator.as_str() {
+            "In" => in_values,
+            "NotIn" => !in_values,
+            "Exists" => value.is_some(),
+            "DoesNotExist" => value.is_none(),
+            _ => false,
+        }
+    });
+
+    labels_match && expressions_match
+}
+
+// Whether labels contain every label of an equality based selector, like the Service one
+pub fn matches_labels(
+    selector: &BTreeMap<String, String>,
+    labels: Option<&BTreeMap<String, String>>,
+) -> bool {
+    selector
+        .iter()
+        .all(|(k, v)| labels.and_then(|l| l.get(k)) == Some(v))
+}
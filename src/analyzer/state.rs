@@ -0,0 +1,393 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use coi::gitops::{self, GitOpsApplication};
+use coi::workloads;
+use k8s_openapi::api::admissionregistration::v1::{
+    MutatingWebhookConfiguration, ValidatingWebhookConfiguration,
+};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{
+    ConfigMap, Container, Endpoints, LimitRange, Namespace, Node, PersistentVolumeClaim, Pod,
+    PodSpec, PodTemplateSpec, ResourceQuota, Secret, Service, ServiceAccount,
+};
+use k8s_openapi::api::networking::v1::{Ingress, NetworkPolicy};
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::api::rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding};
+use k8s_openapi::api::scheduling::v1::PriorityClass;
+use k8s_openapi::api::storage::v1::{CSIDriver, StorageClass};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::apimachinery::pkg::version::Info;
+use kube::api::{DynamicObject, ListParams};
+use kube::discovery::Discovery;
+use log::warn;
+use serde_json::Value;
+
+use crate::issues::ObjectReference;
+
+const CERT_MANAGER_GROUP: &str = "cert-manager.io";
+
+// Missing names of the API server version and of the GitOps applications, which are not a single kind
+pub const SERVER_VERSION: &str = "ServerVersion";
+pub const GITOPS_APPLICATIONS: &str = "GitOpsApplication";
+// Kinds read by workloads(), and by pod_owner() to resolve the owner of pods
+pub const WORKLOAD_KINDS: [&str; 6] = [
+    "Deployment",
+    "StatefulSet",
+    "DaemonSet",
+    "CronJob",
+    "Job",
+    "Pod",
+];
+pub const POD_OWNER_KINDS: [&str; 2] = ["ReplicaSet", "Job"];
+
+// Snapshot of the cluster objects, shared by all analyzers during an analysis run
+#[derive(Default)]
+pub struct ClusterState {
+    pub server_version: Info,
+    pub gitops_applications: Vec<GitOpsApplication>,
+    pub namespaces: Vec<Namespace>,
+    pub limit_ranges: Vec<LimitRange>,
+    pub resource_quotas: Vec<ResourceQuota>,
+    pub nodes: Vec<Node>,
+    pub pods: Vec<Pod>,
+    pub deployments: Vec<Deployment>,
+    pub replicasets: Vec<ReplicaSet>,
+    pub statefulsets: Vec<StatefulSet>,
+    pub daemonsets: Vec<DaemonSet>,
+    pub jobs: Vec<Job>,
+    pub cronjobs: Vec<CronJob>,
+    pub pod_disruption_budgets: Vec<PodDisruptionBudget>,
+    pub horizontal_pod_autoscalers: Vec<HorizontalPodAutoscaler>,
+    pub services: Vec<Service>,
+    pub endpoints: Vec<Endpoints>,
+    pub ingresses: Vec<Ingress>,
+    pub network_policies: Vec<NetworkPolicy>,
+    pub secrets: Vec<Secret>,
+    pub configmaps: Vec<ConfigMap>,
+    pub persistent_volume_claims: Vec<PersistentVolumeClaim>,
+    pub service_accounts: Vec<ServiceAccount>,
+    pub storage_classes: Vec<StorageClass>,
+    pub csi_drivers: Vec<CSIDriver>,
+    pub priority_classes: Vec<PriorityClass>,
+    pub roles: Vec<Role>,
+    pub cluster_roles: Vec<ClusterRole>,
+    pub role_bindings: Vec<RoleBinding>,
+    pub cluster_role_bindings: Vec<ClusterRoleBinding>,
+    pub custom_resource_definitions: Vec<CustomResourceDefinition>,
+    pub mutating_webhook_configurations: Vec<MutatingWebhookConfiguration>,
+    pub validating_webhook_configurations: Vec<ValidatingWebhookConfiguration>,
+    // Usage of the mounted volumes, empty when kubelets stats are not reachable
+    pub volume_usages: Vec<VolumeUsage>,
+    // cert-manager Certificates, empty when cert-manager is not installed
+    pub certificates: Vec<DynamicObject>,
+    // cert-manager Issuers and ClusterIssuers
+    pub issuers: Vec<DynamicObject>,
+    // Monthly price of a GiB of storage, from the apiservice pricing when defined there
+    pub storage_gib_month_price: Option<f64>,
+    // Kinds which could not be listed, the issues of the analyzers reading them are not resolved
+    pub missing: BTreeSet<String>,
+}
+
+// Usage of a persistent volume claim, as reported by the kubelet of the node mounting it
+pub struct VolumeUsage {
+    pub namespace: String,
+    pub claim_name: String,
+    pub used_bytes: f64,
+    pub capacity_bytes: f64,
+}
+
+// Pod template of a workload, or spec of a pod not managed by any controller
+pub struct Workload<'a> {
+    pub object: ObjectReference,
+    // Labels of the pods
+    pub labels: Option<&'a BTreeMap<String, String>>,
+    pub spec: &'a PodSpec,
+}
+
+impl<'a> Workload<'a> {
+    fn new(
+        kind: &str,
+        metadata: &ObjectMeta,
+        pod_metadata: Option<&'a ObjectMeta>,
+        spec: Option<&'a PodSpec>,
+    ) -> Option<Self> {
+        Some(Self {
+            object: ObjectReference::new(
+                kind,
+                metadata.name.as_deref()?,
+                metadata.namespace.as_deref()?,
+            ),
+            labels: pod_metadata.and_then(|m| m.labels.as_ref()),
+            spec: spec?,
+        })
+    }
+
+    fn from_template(
+        kind: &str,
+        metadata: &ObjectMeta,
+        template: Option<&'a PodTemplateSpec>,
+    ) -> Option<Self> {
+        let template = template?;
+        Self::new(
+            kind,
+            metadata,
+            template.metadata.as_ref(),
+            template.spec.as_ref(),
+        )
+    }
+
+    // Init and regular containers
+    pub fn containers(&self) -> impl Iterator<Item = &'a Container> {
+        self.spec
+            .init_containers
+            .iter()
+            .flatten()
+            .chain(self.spec.containers.iter())
+    }
+}
+
+async fn list_all<K>(kube_client: &kube::Client) -> Result<Vec<K>, kube::Error>
+where
+    K: kube::Resource + Clone + serde::de::DeserializeOwned + std::fmt::Debug,
+    K::DynamicType: Default,
+{
+    let api: kube::Api<K> = kube::Api::all(kube_client.clone());
+    Ok(api.list(&ListParams::default()).await?.items)
+}
+
+// Objects could not be fetched: analyze the rest of the cluster without them
+fn or_empty<T>(
+    result: Result<Vec<T>, kube::Error>,
+    kind: &str,
+    missing: &mut BTreeSet<String>,
+) -> Vec<T> {
+    match result {
+        Ok(items) => items,
+        Err(e) => {
+            warn!("Unable to list {}: {}", kind, e);
+            missing.insert(kind.to_string());
+            vec![]
+        }
+    }
+}
+
+async fn list_or_empty<K>(kube_client: &kube::Client, missing: &mut BTreeSet<String>) -> Vec<K>
+where
+    K: kube::Resource + Clone + serde::de::DeserializeOwned + std::fmt::Debug,
+    K::DynamicType: Default,
+{
+    let kind = K::kind(&K::DynamicType::default()).to_string();
+    or_empty(list_all(kube_client).await, &kind, missing)
+}
+
+// List a custom resource of every namespace, if its group is served by the cluster
+async fn list_custom(
+    kube_client: &kube::Client,
+    group: &str,
+    kind: &str,
+) -> Result<Vec<DynamicObject>, kube::Error> {
+    let discovery = Discovery::new(kube_client.clone())
+        .filter(&[group])
+        .run()
+        .await?;
+    let resource = match discovery.get(group).and_then(|g| g.recommended_kind(kind)) {
+        Some((resource, _)) => resource,
+        None => return Ok(vec![]),
+    };
+
+    let api: kube::Api<DynamicObject> = kube::Api::all_with(kube_client.clone(), &resource);
+    Ok(api.list(&ListParams::default()).await?.items)
+}
+
+// Read persistent volumes usage from the kubelets summary API, through the API server proxy
+async fn volume_usages(kube_client: &kube::Client, nodes: &[Node]) -> Vec<VolumeUsage> {
+    let mut usages = vec![];
+
+    for name in nodes.iter().filter_map(|n| n.metadata.name.as_deref()) {
+        let request =
+            match hyper::Request::get(format!("/api/v1/nodes/{}/proxy/stats/summary", name))
+                .body(vec![])
+            {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+        let summary: Value = match kube_client.request(request).await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Unable to read volume stats of node {}: {}", name, e);
+                continue;
+            }
+        };
+
+        let pods = summary.get("pods").and_then(Value::as_array);
+        let volumes = pods
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.get("volume").and_then(Value::as_array))
+            .flatten();
+        for volume in volumes {
+            // Only persistent volumes are reported with their claim
+            let claim = match volume.get("pvcRef") {
+                Some(c) => c,
+                None => continue,
+            };
+            if let (Some(namespace), Some(claim_name), Some(used_bytes), Some(capacity_bytes)) = (
+                claim.get("namespace").and_then(Value::as_str),
+                claim.get("name").and_then(Value::as_str),
+                volume.get("usedBytes").and_then(Value::as_f64),
+                volume.get("capacityBytes").and_then(Value::as_f64),
+            ) {
+                usages.push(VolumeUsage {
+                    namespace: namespace.to_string(),
+                    claim_name: claim_name.to_string(),
+                    used_bytes,
+                    capacity_bytes,
+                });
+            }
+        }
+    }
+
+    usages
+}
+
+pub fn has_controller(metadata: &ObjectMeta) -> bool {
+    metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .any(|o| o.controller.unwrap_or(false))
+}
+
+impl ClusterState {
+    pub async fn fetch(kube_client: &kube::Client) -> Self {
+        let mut missing = BTreeSet::new();
+        let server_version = match kube_client.apiserver_version().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Unable to read the API server version: {}", e);
+                missing.insert(SERVER_VERSION.to_string());
+                Info::default()
+            }
+        };
+        let nodes = list_or_empty(kube_client, &mut missing).await;
+        let volume_usages = volume_usages(kube_client, &nodes).await;
+
+        Self {
+            server_version,
+            gitops_applications: or_empty(
+                gitops::list_applications(kube_client).await,
+                GITOPS_APPLICATIONS,
+                &mut missing,
+            ),
+            namespaces: list_or_empty(kube_client, &mut missing).await,
+            limit_ranges: list_or_empty(kube_client, &mut missing).await,
+            resource_quotas: list_or_empty(kube_client, &mut missing).await,
+            nodes,
+            pods: list_or_empty(kube_client, &mut missing).await,
+            deployments: list_or_empty(kube_client, &mut missing).await,
+            replicasets: list_or_empty(kube_client, &mut missing).await,
+            statefulsets: list_or_empty(kube_client, &mut missing).await,
+            daemonsets: list_or_empty(kube_client, &mut missing).await,
+            jobs: list_or_empty(kube_client, &mut missing).await,
+            cronjobs: list_or_empty(kube_client, &mut missing).await,
+            pod_disruption_budgets: list_or_empty(kube_client, &mut missing).await,
+            horizontal_pod_autoscalers: list_or_empty(kube_client, &mut missing).await,
+            services: list_or_empty(kube_client, &mut missing).await,
+            endpoints: list_or_empty(kube_client, &mut missing).await,
+            ingresses: list_or_empty(kube_client, &mut missing).await,
+            network_policies: list_or_empty(kube_client, &mut missing).await,
+            secrets: list_or_empty(kube_client, &mut missing).await,
+            configmaps: list_or_empty(kube_client, &mut missing).await,
+            persistent_volume_claims: list_or_empty(kube_client, &mut missing).await,
+            service_accounts: list_or_empty(kube_client, &mut missing).await,
+            storage_classes: list_or_empty(kube_client, &mut missing).await,
+            csi_drivers: list_or_empty(kube_client, &mut missing).await,
+            priority_classes: list_or_empty(kube_client, &mut missing).await,
+            roles: list_or_empty(kube_client, &mut missing).await,
+            cluster_roles: list_or_empty(kube_client, &mut missing).await,
+            role_bindings: list_or_empty(kube_client, &mut missing).await,
+            cluster_role_bindings: list_or_empty(kube_client, &mut missing).await,
+            custom_resource_definitions: list_or_empty(kube_client, &mut missing).await,
+            mutating_webhook_configurations: list_or_empty(kube_client, &mut missing).await,
+            validating_webhook_configurations: list_or_empty(kube_client, &mut missing).await,
+            volume_usages,
+            certificates: or_empty(
+                list_custom(kube_client, CERT_MANAGER_GROUP, "Certificate").await,
+                "Certificate",
+                &mut missing,
+            ),
+            issuers: or_empty(
+                list_custom(kube_client, CERT_MANAGER_GROUP, "Issuer").await,
+                "Issuer",
+                &mut missing,
+            )
+            .into_iter()
+            .chain(or_empty(
+                list_custom(kube_client, CERT_MANAGER_GROUP, "ClusterIssuer").await,
+                "ClusterIssuer",
+                &mut missing,
+            ))
+            .collect(),
+            storage_gib_month_price: None,
+            missing,
+        }
+    }
+
+    // Top level workload a pod belongs to
+    pub fn pod_owner(&self, pod: &Pod) -> Option<ObjectReference> {
+        let owner = workloads::owning_workload(pod, &self.replicasets, &self.jobs);
+        Some(ObjectReference::new(
+            &owner.kind,
+            &owner.name,
+            pod.metadata.namespace.as_deref()?,
+        ))
+    }
+
+    // Every pod template of the cluster, so that issues are reported on workloads rather than pods
+    pub fn workloads(&self) -> Vec<Workload<'_>> {
+        let mut workloads = vec![];
+
+        for d in self.deployments.iter() {
+            let template = d.spec.as_ref().map(|s| &s.template);
+            workloads.extend(Workload::from_template("Deployment", &d.metadata, template));
+        }
+        for s in self.statefulsets.iter() {
+            let template = s.spec.as_ref().map(|s| &s.template);
+            workloads.extend(Workload::from_template(
+                "StatefulSet",
+                &s.metadata,
+                template,
+            ));
+        }
+        for d in self.daemonsets.iter() {
+            let template = d.spec.as_ref().map(|s| &s.template);
+            workloads.extend(Workload::from_template("DaemonSet", &d.metadata, template));
+        }
+        for c in self.cronjobs.iter() {
+            let template = c
+                .spec
+                .as_ref()
+                .and_then(|s| s.job_template.spec.as_ref())
+                .map(|s| &s.template);
+            workloads.extend(Workload::from_template("CronJob", &c.metadata, template));
+        }
+        // Jobs created by CronJobs are covered by their CronJob template
+        for j in self.jobs.iter().filter(|j| !has_controller(&j.metadata)) {
+            let template = j.spec.as_ref().map(|s| &s.template);
+            workloads.extend(Workload::from_template("Job", &j.metadata, template));
+        }
+        for p in self.pods.iter().filter(|p| !has_controller(&p.metadata)) {
+            workloads.extend(Workload::new(
+                "Pod",
+                &p.metadata,
+                Some(&p.metadata),
+                p.spec.as_ref(),
+            ));
+        }
+
+        workloads
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap};
+
+use coi::resources::{self, ResourceAmounts};
+use coi::workloads::{self, WorkloadRef};
+use k8s_openapi::api::apps::v1::ReplicaSet;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{Node, Pod};
+use kube::api::ListParams;
+use log::warn;
+
+use crate::api::billing::{
+    CostAllocationReport, NamespaceAllocation, OverheadAllocation, Pricing, WorkloadAllocation,
+    HOURS_PER_MONTH,
+};
+use crate::cost::ResourcePricing;
+
+const INSTANCE_TYPE_LABELS: [&str; 2] = [
+    "node.kubernetes.io/instance-type",
+    "beta.kubernetes.io/instance-type",
+];
+
+#[derive(Clone, Debug)]
+pub struct AllocationSettings {
+    pub overhead_allocation: OverheadAllocation,
+    pub system_namespaces: Vec<String>,
+}
+
+struct PodShare<'a> {
+    pod: &'a Pod,
+    share: f64,
+}
+
+// Hourly cost allocated to a running pod
+pub struct PodCost<'a> {
+    pub pod: &'a Pod,
+    pub direct: f64,
+    pub overhead: f64,
+}
+
+// Node costs split between their pods, and the part left to the cluster buckets
+struct Allocation<'a> {
+    nodes_cost_per_hour: f64,
+    unallocated_cost_per_hour: f64,
+    system_cost_per_hour: f64,
+    pods: Vec<PodCost<'a>>,
+}
+
+#[derive(Default)]
+struct WorkloadCost {
+    pods: u32,
+    direct: f64,
+    overhead: f64,
+}
+
+fn node_hourly_cost(
+    node: &Node,
+    allocatable: &ResourceAmounts,
+    node_pricings: &HashMap<String, Pricing>,
+    resource_pricing: &ResourcePricing,
+) -> f64 {
+    let instance_type = node.metadata.labels.as_ref().and_then(|labels| {
+        INSTANCE_TYPE_LABELS
+            .iter()
+            .find_map(|label| labels.get(*label))
+    });
+
+    match instance_type.and_then(|t| node_pricings.get(t)) {
+        Some(pricing) => pricing.hourly_price(),
+        None => {
+            warn!(
+                "No pricing defined for instance type {:?} of node {}, using resource pricing",
+                instance_type,
+                node.metadata.name.as_deref().unwrap_or_default()
+            );
+            resource_pricing.hourly_cost(allocatable)
+        }
+    }
+}
+
+// Share of the node reserved by the pod, averaging its CPU and memory shares
+fn pod_share(pod: &Pod, allocatable: &ResourceAmounts) -> f64 {
+    let requests = match pod.spec.as_ref() {
+        Some(spec) => resources::pod_requests(spec),
+        None => return 0.0,
+    };
+
+    let mut shares = vec![];
+    if allocatable.cpu_cores > 0.0 {
+        shares.push(requests.cpu_cores / allocatable.cpu_cores);
+    }
+    if allocatable.memory_bytes > 0.0 {
+        shares.push(requests.memory_bytes / allocatable.memory_bytes);
+    }
+    if shares.is_empty() {
+        return 0.0;
+    }
+    shares.iter().sum::<f64>() / shares.len() as f64
+}
+
+// Split the cost of every node between the pods it runs, in proportion of their requests
+fn allocate<'a>(
+    nodes: &[Node],
+    pods: &'a [Pod],
+    node_pricings: &HashMap<String, Pricing>,
+    resource_pricing: &ResourcePricing,
+    settings: &AllocationSettings,
+) -> Allocation<'a> {
+    let mut allocation = Allocation {
+        nodes_cost_per_hour: 0.0,
+        unallocated_cost_per_hour: 0.0,
+        system_cost_per_hour: 0.0,
+        pods: vec![],
+    };
+
+    for node in nodes.iter() {
+        let node_name = match node.metadata.name.as_ref() {
+            Some(n) => n,
+            None => continue,
+        };
+        let allocatable = ResourceAmounts::from_resource_list(
+            node.status.as_ref().and_then(|s| s.allocatable.as_ref()),
+        );
+        let node_cost = node_hourly_cost(node, &allocatable, node_pricings, resource_pricing);
+        allocation.nodes_cost_per_hour += node_cost;
+
+        let mut system_shares = 0.0;
+        let mut workload_pods: Vec<PodShare> = vec![];
+        for pod in pods.iter() {
+            if pod.spec.as_ref().and_then(|s| s.node_name.as_ref()) != Some(node_name) {
+                continue;
+            }
+
+            let share = pod_share(pod, &allocatable);
+            let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+            if settings.system_namespaces.contains(&namespace) {
+                system_shares += share;
+            } else {
+                workload_pods.push(PodShare { pod, share });
+            }
+        }
+
+        // Requests can't exceed allocatable capacity, but shares are averaged: keep them in bounds
+        let workload_shares: f64 = workload_pods.iter().map(|p| p.share).sum();
+        let total_shares = workload_shares + system_shares;
+        let scale = if total_shares > 1.0 {
+            1.0 / total_shares
+        } else {
+            1.0
+        };
+
+        let system_cost = system_shares * scale * node_cost;
+        let unallocated_cost = (1.0 - total_shares * scale) * node_cost;
+        let redistribute = settings.overhead_allocation == OverheadAllocation::Proportional
+            && workload_shares > 0.0;
+        if !redistribute {
+            allocation.system_cost_per_hour += system_cost;
+            allocation.unallocated_cost_per_hour += unallocated_cost;
+        }
+
+        for pod_share in workload_pods {
+            allocation.pods.push(PodCost {
+                pod: pod_share.pod,
+                direct: pod_share.share * scale * node_cost,
+                overhead: if redistribute {
+                    (system_cost + unallocated_cost) * pod_share.share / workload_shares
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+
+    allocation
+}
+
+async fn list_running_pods(kube_client: &kube::Client) -> Result<Vec<Pod>, kube::Error> {
+    let pods: kube::Api<Pod> = kube::Api::all(kube_client.clone());
+    Ok(pods
+        .list(&ListParams::default().fields("status.phase=Running"))
+        .await?
+        .items)
+}
+
+// Hourly cost of every running pod, as allocated from the cost of its node
+pub async fn pod_hourly_costs(
+    kube_client: &kube::Client,
+    node_pricings: &HashMap<String, Pricing>,
+    resource_pricing: &ResourcePricing,
+    settings: &AllocationSettings,
+) -> Result<Vec<(Pod, f64)>, kube::Error> {
+    let nodes: kube::Api<Node> = kube::Api::all(kube_client.clone());
+    let nodes = nodes.list(&ListParams::default()).await?.items;
+    let pods = list_running_pods(kube_client).await?;
+
+    let allocation = allocate(&nodes, &pods, node_pricings, resource_pricing, settings);
+    Ok(allocation
+        .pods
+        .into_iter()
+        .map(|c| (c.pod.clone(), c.direct + c.overhead))
+        .collect())
+}
+
+// Allocate the cost of every node to the pods it runs, in proportion of their requests
+pub async fn allocate_node_costs(
+    kube_client: &kube::Client,
+    node_pricings: &HashMap<String, Pricing>,
+    resource_pricing: &ResourcePricing,
+    settings: &AllocationSettings,
+) -> Result<CostAllocationReport, kube::Error> {
+    let nodes: kube::Api<Node> = kube::Api::all(kube_client.clone());
+    let replicasets: kube::Api<ReplicaSet> = kube::Api::all(kube_client.clone());
+    let jobs: kube::Api<Job> = kube::Api::all(kube_client.clone());
+
+    let nodes = nodes.list(&ListParams::default()).await?.items;
+    let pods = list_running_pods(kube_client).await?;
+    let replicasets = replicasets.list(&ListParams::default()).await?.items;
+    let jobs = jobs.list(&ListParams::default()).await?.items;
+
+    let allocation = allocate(&nodes, &pods, node_pricings, resource_pricing, settings);
+    let mut report = CostAllocationReport {
+        overhead_allocation: settings.overhead_allocation,
+        nodes_cost_per_hour: Some(allocation.nodes_cost_per_hour),
+        unallocated_cost_per_hour: Some(allocation.unallocated_cost_per_hour),
+        system_cost_per_hour: Some(allocation.system_cost_per_hour),
+        namespaces: vec![],
+    };
+    let mut costs: BTreeMap<String, BTreeMap<WorkloadRef, WorkloadCost>> = BTreeMap::new();
+
+    for pod_cost in allocation.pods {
+        let workload = workloads::owning_workload(pod_cost.pod, &replicasets, &jobs);
+        let namespace = pod_cost.pod.metadata.namespace.clone().unwrap_or_default();
+        let cost = costs
+            .entry(namespace)
+            .or_default()
+            .entry(workload)
+            .or_default();
+
+        cost.pods += 1;
+        cost.direct += pod_cost.direct;
+        cost.overhead += pod_cost.overhead;
+    }
+
+    for (namespace, workloads) in costs {
+        let mut namespace_allocation = NamespaceAllocation {
+            namespace,
+            cost_per_hour: 0.0,
+            cost_per_month: 0.0,
+            workloads: vec![],
+        };
+
+        for (workload, cost) in workloads {
+            let cost_per_hour = cost.direct + cost.overhead;
+            namespace_allocation.cost_per_hour += cost_per_hour;
+            namespace_allocation.workloads.push(WorkloadAllocation {
+                workload_type: workload.kind,
+                workload_name: workload.name,
+                pods: cost.pods,
+                direct_cost_per_hour: cost.direct,
+                overhead_cost_per_hour: cost.overhead,
+                cost_per_hour,
+                cost_per_month: cost_per_hour * HOURS_PER_MONTH,
+            });
+        }
+
+        namespace_allocation.cost_per_month = namespace_allocation.cost_per_hour * HOURS_PER_MONTH;
+        report.namespaces.push(namespace_allocation);
+    }
+
+    Ok(report)
+}
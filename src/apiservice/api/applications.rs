@@ -1,16 +1,48 @@
-use axum::extract::Path;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use coi::gitops::{self, GitOpsApplication};
+use log::error;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::api::helpers;
 
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct GitOpsApplicationList {
+    pub applications: Vec<GitOpsApplication>,
+}
+
 #[utoipa::path(
 	get,
-	path = "/v1/applications/gitops/:namespace",
+	path = "/v1/applications/gitops/{namespace}",
 	responses(
-		(status = 200, description = "List all gitops applications successfully", body = [BillingResult])
+		(status = 200, description = "List all gitops applications successfully", body = GitOpsApplicationList),
+		(status = 403, description = "Forbidden"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("namespace", Path, description = "Namespace name, matching applications defined in or deploying to it")
 	)
 )]
-pub async fn list_gitops_applications(Path(namespace): Path<String>) -> String {
-    let (_username, _groups) = helpers::get_user_context();
+pub async fn list_gitops_applications(
+    Extension(kube_client): Extension<kube::Client>,
+    Path(namespace): Path<String>,
+) -> Result<Json<GitOpsApplicationList>, StatusCode> {
+    helpers::check_namespace_rights(&kube_client, &namespace).await?;
+
+    let applications = match gitops::list_applications(&kube_client).await {
+        Ok(a) => a,
+        Err(e) => {
+            error!("Error while listing gitops applications: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
 
-    format!("List GitOps Applications {}", namespace)
+    Ok(Json(GitOpsApplicationList {
+        applications: applications
+            .into_iter()
+            .filter(|a| {
+                a.namespace == namespace || a.destination_namespace.as_ref() == Some(&namespace)
+            })
+            .collect(),
+    }))
 }
@@ -1,7 +1,16 @@
-use axum::{http::StatusCode, Json};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use log::error;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+pub use coi::pricing::{PricingPeriod, HOURS_PER_MONTH};
+
+use crate::allocation::{self, AllocationSettings};
+use crate::cost::{self, IdleCostSettings};
+use crate::db::Database;
+use crate::usage;
+
+use super::cluster::ClusterIdentity;
 use super::helpers;
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
@@ -17,6 +26,88 @@ pub struct BillingResult {
     status: String,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Pricing {
+    pub price: f64,
+    pub period: PricingPeriod,
+    pub description: String,
+}
+
+impl Pricing {
+    pub fn hourly_price(&self) -> f64 {
+        self.price / self.period.hours()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct WorkloadIdleCost {
+    #[schema(example = "Deployment")]
+    pub workload_type: String,
+    pub workload_name: String,
+    pub pods: u32,
+    pub cpu_requested_cores: f64,
+    pub cpu_used_cores: f64,
+    pub memory_requested_bytes: f64,
+    pub memory_used_bytes: f64,
+    pub idle_cpu_cost_per_hour: f64,
+    pub idle_memory_cost_per_hour: f64,
+    pub idle_cost_per_hour: f64,
+    pub idle_cost_per_month: f64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct IdleCostReport {
+    pub namespace: String,
+    #[schema(example = "metrics.k8s.io")]
+    pub usage_source: String,
+    pub idle_cost_per_hour: f64,
+    pub idle_cost_per_month: f64,
+    pub workloads: Vec<WorkloadIdleCost>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverheadAllocation {
+    // Spread unallocated capacity and system overhead on workloads of the same node
+    Proportional,
+    // Keep unallocated capacity and system overhead in dedicated buckets
+    Bucket,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct WorkloadAllocation {
+    #[schema(example = "Deployment")]
+    pub workload_type: String,
+    pub workload_name: String,
+    pub pods: u32,
+    // Cost of the node share reserved by the workload requests
+    pub direct_cost_per_hour: f64,
+    // Unallocated capacity and system overhead attributed to the workload
+    pub overhead_cost_per_hour: f64,
+    pub cost_per_hour: f64,
+    pub cost_per_month: f64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct NamespaceAllocation {
+    pub namespace: String,
+    pub cost_per_hour: f64,
+    pub cost_per_month: f64,
+    pub workloads: Vec<WorkloadAllocation>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct CostAllocationReport {
+    pub overhead_allocation: OverheadAllocation,
+    // Cluster wide totals, only returned with cluster wide rights
+    pub nodes_cost_per_hour: Option<f64>,
+    // Node capacity not reserved by any pod and left in its own bucket
+    pub unallocated_cost_per_hour: Option<f64>,
+    // System namespaces cost left in its own bucket, only returned with rights on every system namespace
+    pub system_cost_per_hour: Option<f64>,
+    pub namespaces: Vec<NamespaceAllocation>,
+}
+
 #[utoipa::path(
 	post,
 	path = "/v1/billing/pod",
@@ -32,3 +123,224 @@ pub async fn post_pod_invoice() -> (StatusCode, Json<BillingResult>) {
     };
     (StatusCode::CREATED, Json(r.clone()))
 }
+
+#[utoipa::path(
+	get,
+	path = "/v1/billing/idle/{namespace}",
+	responses(
+		(status = 200, description = "Idle reserved capacity cost, based on metrics.k8s.io usage", body = IdleCostReport),
+		(status = 403, description = "Forbidden"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("namespace", Path, description = "Namespace name")
+	)
+)]
+pub async fn get_idle_cost(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Path(namespace): Path<String>,
+) -> Result<Json<IdleCostReport>, StatusCode> {
+    helpers::check_namespace_rights(&kube_client, &namespace).await?;
+
+    let pod_usage = match usage::from_metrics_api(&kube_client, &namespace).await {
+        Ok(u) => u,
+        Err(e) => {
+            error!(
+                "Unable to fetch pod metrics for namespace {}: {}",
+                namespace, e
+            );
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let report = build_idle_cost_report(
+        &db,
+        &kube_client,
+        &namespace,
+        &pod_usage,
+        usage::METRICS_API_SOURCE,
+    )
+    .await?;
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+	post,
+	path = "/v1/billing/idle/{namespace}",
+	request_body(content = String, description = "Prometheus text format scrape", content_type = "text/plain"),
+	responses(
+		(status = 200, description = "Idle reserved capacity cost, based on the provided scrape. Over-provisioned Deployments are reported as issues", body = IdleCostReport),
+		(status = 403, description = "Forbidden"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("namespace", Path, description = "Namespace name")
+	)
+)]
+pub async fn post_idle_cost(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Extension(cluster_identity): Extension<ClusterIdentity>,
+    Extension(settings): Extension<IdleCostSettings>,
+    Path(namespace): Path<String>,
+    scrape: String,
+) -> Result<Json<IdleCostReport>, StatusCode> {
+    helpers::check_namespace_rights(&kube_client, &namespace).await?;
+
+    let pod_usage = usage::from_prometheus_text(&scrape, &namespace);
+    let report = build_idle_cost_report(
+        &db,
+        &kube_client,
+        &namespace,
+        &pod_usage,
+        usage::PROMETHEUS_SOURCE,
+    )
+    .await?;
+
+    if let Err(e) =
+        cost::record_idle_cost_issues(&db, cluster_identity.name(), &report, &settings).await
+    {
+        error!("Unable to record idle cost issues: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+	get,
+	path = "/v1/billing/allocation",
+	responses(
+		(status = 200, description = "Node costs allocated to the workloads of the namespaces the user can access", body = CostAllocationReport),
+		(status = 500, description = "Server error")
+	)
+)]
+pub async fn get_cost_allocation(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Extension(settings): Extension<AllocationSettings>,
+) -> Result<Json<CostAllocationReport>, StatusCode> {
+    let (username, groups) = helpers::get_user_context();
+
+    let node_pricings = match db.get_node_pricings().await {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Unable to run db.get_node_pricings : {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let resource_pricing = match cost::load_resource_pricing(&db).await {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Unable to load resource pricing: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let mut report = match allocation::allocate_node_costs(
+        &kube_client,
+        &node_pricings,
+        &resource_pricing,
+        &settings,
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to allocate node costs: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let mut allowed_namespaces = vec![];
+    for namespace in report.namespaces.drain(..) {
+        if is_allowed(&kube_client, &namespace.namespace, &username, &groups).await? {
+            allowed_namespaces.push(namespace);
+        }
+    }
+    report.namespaces = allowed_namespaces;
+
+    // Buckets aggregate namespaces the user may not be allowed on
+    if !is_allowed(&kube_client, "", &username, &groups).await? {
+        report.nodes_cost_per_hour = None;
+        report.unallocated_cost_per_hour = None;
+    }
+    for namespace in settings.system_namespaces.iter() {
+        if !is_allowed(&kube_client, namespace, &username, &groups).await? {
+            report.system_cost_per_hour = None;
+            break;
+        }
+    }
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+	get,
+	path = "/v1/billing/pricing/{object_type}",
+	responses(
+		(status = 200, description = "Pricing of the object type", body = Pricing),
+		(status = 404, description = "No pricing defined for the object type"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("object_type", Path, description = "Priced object type: cpu, memory or storage")
+	)
+)]
+pub async fn get_pricing(
+    Extension(db): Extension<Database>,
+    Path(object_type): Path<String>,
+) -> Result<Json<Pricing>, StatusCode> {
+    match db.get_pricing_for_object_type(&object_type).await {
+        Ok(Some(p)) => Ok(Json(p)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Unable to run db.get_pricing_for_object_type : {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn is_allowed(
+    kube_client: &kube::Client,
+    namespace: &str,
+    username: &str,
+    groups: &[String],
+) -> Result<bool, StatusCode> {
+    helpers::has_rights(kube_client, namespace, username, groups)
+        .await
+        .map_err(|e| {
+            error!("Error while checking rights: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn build_idle_cost_report(
+    db: &Database,
+    kube_client: &kube::Client,
+    namespace: &str,
+    pod_usage: &usage::PodUsage,
+    usage_source: &str,
+) -> Result<IdleCostReport, StatusCode> {
+    let pricing = match cost::load_resource_pricing(db).await {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Unable to load resource pricing: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match cost::estimate_idle_cost(kube_client, namespace, pod_usage, &pricing, usage_source).await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!(
+                "Unable to estimate idle cost for namespace {}: {}",
+                namespace, e
+            );
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use log::error;
+use postgres_types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::api::helpers;
+use crate::cost;
+use crate::db::Database;
+
+#[derive(Debug, FromSql, ToSql, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[postgres(name = "budget_scope", rename_all = "lowercase")]
+pub enum BudgetScope {
+    Namespace,
+    Label,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Budget {
+    pub name: String,
+    pub scope: BudgetScope,
+    // Namespace the budget applies to, for namespace scoped budgets
+    pub namespace: Option<String>,
+    // Pod label the budget applies to, across namespaces, for label scoped budgets
+    pub label_key: Option<String>,
+    pub label_value: Option<String>,
+    pub monthly_amount: f64,
+    // Ratios of the monthly amount raising an issue once reached
+    #[schema(example = json!([0.8, 1.0]))]
+    pub thresholds: Vec<f64>,
+}
+
+impl Budget {
+    fn is_valid(&self) -> bool {
+        let target_set = match self.scope {
+            BudgetScope::Namespace => self.namespace.is_some(),
+            BudgetScope::Label => self.label_key.is_some() && self.label_value.is_some(),
+        };
+        target_set && self.monthly_amount > 0.0 && self.thresholds.iter().all(|t| *t > 0.0)
+    }
+
+    // Label budgets span namespaces, they require cluster wide rights
+    fn rights_namespace(&self) -> &str {
+        match self.scope {
+            BudgetScope::Namespace => self.namespace.as_deref().unwrap_or_default(),
+            BudgetScope::Label => "",
+        }
+    }
+}
+
+async fn find_budget(db: &Database, name: &str) -> Result<Option<Budget>, StatusCode> {
+    match db.get_budgets_with_spending().await {
+        Ok(budgets) => Ok(budgets
+            .into_iter()
+            .map(|(_, budget, _)| budget)
+            .find(|b| b.name == name)),
+        Err(e) => {
+            error!("Unable to run db.get_budgets_with_spending : {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub month_to_date: f64,
+    // Linear projection of the month to date cost to the end of the month
+    pub forecast: f64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct BudgetList {
+    pub budgets: Vec<BudgetStatus>,
+}
+
+#[utoipa::path(
+	get,
+	path = "/v1/budgets",
+	responses(
+		(status = 200, description = "List budgets with their month to date cost", body = BudgetList),
+		(status = 500, description = "Server error")
+	)
+)]
+pub async fn list_budgets(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+) -> Result<Json<BudgetList>, StatusCode> {
+    let (username, groups) = helpers::get_user_context();
+
+    let budgets = match db.get_budgets_with_spending().await {
+        Ok(b) => b,
+        Err(e) => {
+            error!("Unable to run db.get_budgets_with_spending : {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let progress = cost::month_progress();
+    let mut r = BudgetList { budgets: vec![] };
+    for (_, budget, month_to_date) in budgets {
+        match helpers::has_rights(&kube_client, budget.rights_namespace(), &username, &groups).await
+        {
+            Ok(true) => r.budgets.push(BudgetStatus {
+                budget,
+                month_to_date,
+                forecast: cost::forecast(month_to_date, progress),
+            }),
+            Ok(false) => {}
+            Err(e) => {
+                error!("Error while checking rights: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    Ok(Json(r))
+}
+
+#[utoipa::path(
+	post,
+	path = "/v1/budgets",
+	request_body = Budget,
+	responses(
+		(status = 200, description = "Budget created or updated"),
+		(status = 400, description = "Invalid budget"),
+		(status = 403, description = "Not allowed on the budget namespace"),
+		(status = 500, description = "Server error")
+	)
+)]
+pub async fn store_budget(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Json(mut budget): Json<Budget>,
+) -> (StatusCode, &'static str) {
+    if budget.thresholds.is_empty() {
+        budget.thresholds = vec![0.8, 1.0];
+    }
+    if !budget.is_valid() {
+        return (StatusCode::BAD_REQUEST, "Invalid budget");
+    }
+
+    // Updating a budget requires rights on both its previous and new targets
+    let existing = match find_budget(&db, &budget.name).await {
+        Ok(b) => b,
+        Err(status) => return (status, "Unable to run db.get_budgets_with_spending"),
+    };
+    for target in existing.iter().chain(std::iter::once(&budget)) {
+        if let Err(status) =
+            helpers::check_namespace_rights(&kube_client, target.rights_namespace()).await
+        {
+            return (status, "Not allowed on the budget namespace");
+        }
+    }
+
+    match db.store_budget(&budget).await {
+        Ok(_) => (StatusCode::OK, "{}"),
+        Err(e) => {
+            error!("Unable to run db.store_budget : {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unable to run db.store_budget",
+            )
+        }
+    }
+}
+
+#[utoipa::path(
+	delete,
+	path = "/v1/budgets/{name}",
+	responses(
+		(status = 200, description = "Budget deleted"),
+		(status = 403, description = "Not allowed on the budget namespace"),
+		(status = 404, description = "Budget not found"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("name", Path, description = "Budget name")
+	)
+)]
+pub async fn delete_budget(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Path(name): Path<String>,
+) -> (StatusCode, &'static str) {
+    let budget = match find_budget(&db, &name).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Budget not found"),
+        Err(status) => return (status, "Unable to run db.get_budgets_with_spending"),
+    };
+    if let Err(status) =
+        helpers::check_namespace_rights(&kube_client, budget.rights_namespace()).await
+    {
+        return (status, "Not allowed on the budget namespace");
+    }
+
+    match db.delete_budget(&name).await {
+        Ok(true) => (StatusCode::OK, "{}"),
+        Ok(false) => (StatusCode::NOT_FOUND, "Budget not found"),
+        Err(e) => {
+            error!("Unable to run db.delete_budget : {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unable to run db.delete_budget",
+            )
+        }
+    }
+}
@@ -11,6 +11,10 @@ impl ClusterIdentity {
     pub fn new(name: String) -> Self {
         Self { name }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 #[utoipa::path(
@@ -1,14 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use coi::resources::{self, ResourceAmounts};
+use coi::workloads::{self, WorkloadRef};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{Pod, PodTemplateSpec};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::ListParams;
+use log::error;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
 use super::helpers;
 
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ContainerResources {
+    pub name: String,
+    pub image: Option<String>,
+    #[schema(example = "100m")]
+    pub cpu_request: Option<String>,
+    pub cpu_limit: Option<String>,
+    #[schema(example = "128Mi")]
+    pub memory_request: Option<String>,
+    pub memory_limit: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ComputeWorkload {
+    #[schema(example = "Deployment")]
+    pub workload_type: String,
+    pub name: String,
+    pub replicas: i32,
+    pub ready_replicas: i32,
+    pub containers: Vec<ContainerResources>,
+    // Sum of the requests of the workload pods
+    pub reserved_cpu_cores: f64,
+    pub reserved_memory_bytes: f64,
+    // Nodes running the workload pods
+    pub nodes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ComputeList {
+    pub namespace: String,
+    pub workloads: Vec<ComputeWorkload>,
+}
+
+#[derive(Default)]
+struct PodPlacement {
+    reserved: ResourceAmounts,
+    nodes: BTreeSet<String>,
+}
+
+fn container_resources(template: &PodTemplateSpec) -> Vec<ContainerResources> {
+    let containers = match template.spec.as_ref() {
+        Some(spec) => &spec.containers,
+        None => return vec![],
+    };
+
+    containers
+        .iter()
+        .map(|c| {
+            let requests = c.resources.as_ref().and_then(|r| r.requests.as_ref());
+            let limits = c.resources.as_ref().and_then(|r| r.limits.as_ref());
+            let quantity = |list: Option<&BTreeMap<String, Quantity>>, name: &str| {
+                list.and_then(|l| l.get(name)).map(|q| q.0.clone())
+            };
+            ContainerResources {
+                name: c.name.clone(),
+                image: c.image.clone(),
+                cpu_request: quantity(requests, "cpu"),
+                cpu_limit: quantity(limits, "cpu"),
+                memory_request: quantity(requests, "memory"),
+                memory_limit: quantity(limits, "memory"),
+            }
+        })
+        .collect()
+}
+
+fn compute_workload(
+    workload_type: &str,
+    name: Option<&String>,
+    replicas: i32,
+    ready_replicas: i32,
+    template: &PodTemplateSpec,
+    placements: &mut BTreeMap<WorkloadRef, PodPlacement>,
+) -> ComputeWorkload {
+    let name = name.cloned().unwrap_or_default();
+    let placement = placements
+        .remove(&WorkloadRef {
+            kind: workload_type.to_string(),
+            name: name.clone(),
+        })
+        .unwrap_or_default();
+
+    ComputeWorkload {
+        workload_type: workload_type.to_string(),
+        name,
+        replicas,
+        ready_replicas,
+        containers: container_resources(template),
+        reserved_cpu_cores: placement.reserved.cpu_cores,
+        reserved_memory_bytes: placement.reserved.memory_bytes,
+        nodes: placement.nodes.into_iter().collect(),
+    }
+}
+
+async fn list_compute(
+    kube_client: &kube::Client,
+    namespace: &str,
+) -> Result<ComputeList, kube::Error> {
+    let lp = ListParams::default();
+    let deployments: kube::Api<Deployment> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let statefulsets: kube::Api<StatefulSet> =
+        kube::Api::namespaced(kube_client.clone(), namespace);
+    let daemonsets: kube::Api<DaemonSet> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let jobs: kube::Api<Job> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let replicasets: kube::Api<ReplicaSet> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let pods: kube::Api<Pod> = kube::Api::namespaced(kube_client.clone(), namespace);
+
+    let replicasets = replicasets.list(&lp).await?.items;
+    let mut placements: BTreeMap<WorkloadRef, PodPlacement> = BTreeMap::new();
+    for pod in pods.list(&lp).await?.items {
+        // Completed pods don't reserve anything anymore
+        let phase = pod.status.as_ref().and_then(|s| s.phase.as_deref());
+        if matches!(phase, Some("Succeeded") | Some("Failed")) {
+            continue;
+        }
+        let spec = match pod.spec.as_ref() {
+            Some(s) => s,
+            None => continue,
+        };
+
+        // Jobs are listed on their own, pods are not resolved up to their CronJob
+        let placement = placements
+            .entry(workloads::owning_workload(&pod, &replicasets, &[]))
+            .or_default();
+        placement.reserved.add(&resources::pod_requests(spec));
+        if let Some(node) = spec.node_name.as_ref() {
+            placement.nodes.insert(node.clone());
+        }
+    }
+
+    let mut result = ComputeList {
+        namespace: namespace.to_string(),
+        workloads: vec![],
+    };
+
+    for d in deployments.list(&lp).await?.items {
+        let spec = d.spec.unwrap_or_default();
+        let status = d.status.unwrap_or_default();
+        result.workloads.push(compute_workload(
+            "Deployment",
+            d.metadata.name.as_ref(),
+            spec.replicas.unwrap_or(1),
+            status.ready_replicas.unwrap_or(0),
+            &spec.template,
+            &mut placements,
+        ));
+    }
+
+    for s in statefulsets.list(&lp).await?.items {
+        let spec = s.spec.unwrap_or_default();
+        let status = s.status.unwrap_or_default();
+        result.workloads.push(compute_workload(
+            "StatefulSet",
+            s.metadata.name.as_ref(),
+            spec.replicas.unwrap_or(1),
+            status.ready_replicas.unwrap_or(0),
+            &spec.template,
+            &mut placements,
+        ));
+    }
+
+    for d in daemonsets.list(&lp).await?.items {
+        let spec = d.spec.unwrap_or_default();
+        let status = d.status.unwrap_or_default();
+        result.workloads.push(compute_workload(
+            "DaemonSet",
+            d.metadata.name.as_ref(),
+            status.desired_number_scheduled,
+            status.number_ready,
+            &spec.template,
+            &mut placements,
+        ));
+    }
+
+    for j in jobs.list(&lp).await?.items {
+        let spec = j.spec.unwrap_or_default();
+        let status = j.status.unwrap_or_default();
+        result.workloads.push(compute_workload(
+            "Job",
+            j.metadata.name.as_ref(),
+            spec.parallelism.unwrap_or(1),
+            status.ready.unwrap_or(0),
+            &spec.template,
+            &mut placements,
+        ));
+    }
+
+    Ok(result)
+}
+
 #[utoipa::path(
 	get,
-	path = "/v1/compute/:namespace",
+	path = "/v1/compute/{namespace}",
 	responses(
-		(status = 200, description = "List all compute successfully")
+		(status = 200, description = "List all compute successfully", body = ComputeList),
+		(status = 403, description = "Forbidden"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("namespace", Path, description = "Namespace name")
 	)
 )]
-pub async fn list() -> &'static str {
-    let (_username, _groups) = helpers::get_user_context();
+pub async fn list(
+    Extension(kube_client): Extension<kube::Client>,
+    Path(namespace): Path<String>,
+) -> Result<Json<ComputeList>, StatusCode> {
+    helpers::check_namespace_rights(&kube_client, &namespace).await?;
 
-    "List Compute"
+    match list_compute(&kube_client, &namespace).await {
+        Ok(r) => Ok(Json(r)),
+        Err(e) => {
+            error!(
+                "Error while listing workloads of namespace {}: {}",
+                namespace, e
+            );
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
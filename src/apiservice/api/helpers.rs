@@ -1,12 +1,14 @@
+use hyper::StatusCode;
 use k8s_openapi::api::authorization::v1::SubjectAccessReview;
 use kube::api::PostParams;
 use kube::core::ObjectMeta;
+use log::error;
 
 pub async fn has_rights(
     kube_client: &kube::Client,
     namespace: &str,
     username: &str,
-    groups: &Vec<String>,
+    groups: &[String],
 ) -> Result<bool, kube::Error> {
     let review_api: kube::Api<SubjectAccessReview> = kube::Api::all(kube_client.clone());
 
@@ -47,6 +49,22 @@ pub async fn has_rights(
     Ok(review_result.status.unwrap().allowed)
 }
 
+// Check current user rights on namespace, mapping failures to the HTTP status to return
+pub async fn check_namespace_rights(
+    kube_client: &kube::Client,
+    namespace: &str,
+) -> Result<(), StatusCode> {
+    let (username, groups) = get_user_context();
+    match has_rights(kube_client, namespace, &username, &groups).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(StatusCode::FORBIDDEN),
+        Err(e) => {
+            error!("Error while checking rights: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 // TODO implement this check
 pub fn get_user_context() -> (String, Vec<String>) {
     let username = "admin";
@@ -8,7 +8,7 @@ use uuid::Uuid;
 use crate::db::Database;
 
 use super::helpers;
-use super::objects::NamespacedObject;
+use super::objects::{NamespacedObject, ObjectReference};
 
 #[derive(Debug, FromSql, ToSql, Deserialize, Serialize, Clone, ToSchema)]
 #[postgres(name = "issue_category", rename_all = "lowercase")]
@@ -17,6 +17,7 @@ pub enum IssueCategory {
     Reliability,
     Performance,
     Configuration,
+    Cost,
     Unknown,
 }
 
@@ -64,11 +65,22 @@ pub struct PostIssue {
     #[schema(read_only = true)]
     last_seen_at: Option<String>,
     linked_object_id: Option<String>,
+    // Object to link the issue to, recorded on the fly when its id is unknown to the reporter
+    linked_object: Option<ObjectReference>,
+}
+
+// Issues of the reporters on the cluster which are not in the list are resolved
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct IssueResolution {
+    pub cluster: String,
+    #[schema(example = json!(["analyzer/probes"]))]
+    pub reporters: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
 pub struct IssueList {
     pub issues: Vec<PostIssue>,
+    pub resolution: Option<IssueResolution>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
@@ -121,21 +133,57 @@ pub async fn list_issues_by_category(
         }
     }
 
+    issues_in_namespace(&db, category, &namespace_name)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct IssuesClusterParams {
+    category: IssueCategory,
+}
+
+#[utoipa::path(
+	get,
+	path = "/v1/issues/{category}",
+	responses(
+		(status = 200, description = "List issues of cluster scoped objects successfully", body=IssueListWithObjects),
+		(status = 403, description = "Forbidden"),
+		(status = 500, description = "Server error")
+	),
+	params(
+		("issue_type", Path, description = "Issue type")
+	)
+)]
+pub async fn list_cluster_issues_by_category(
+    Extension(db): Extension<Database>,
+    Extension(kube_client): Extension<kube::Client>,
+    Path(IssuesClusterParams { category }): Path<IssuesClusterParams>,
+) -> Result<Json<IssueListWithObjects>, StatusCode> {
+    // Cluster scoped objects are recorded without namespace, they require cluster wide rights
+    helpers::check_namespace_rights(&kube_client, "").await?;
+
+    issues_in_namespace(&db, category, "").await.map(Json)
+}
+
+async fn issues_in_namespace(
+    db: &Database,
+    category: IssueCategory,
+    namespace_name: &str,
+) -> Result<IssueListWithObjects, StatusCode> {
     let mut r = IssueListWithObjects { issues: vec![] };
 
-    r.issues = match db
-        .get_objects_with_issue_category_in_namespace(category.clone(), &namespace_name)
+    match db
+        .get_objects_with_issue_category_in_namespace(category.clone(), namespace_name)
         .await
     {
         Ok(objects) => {
-            let issues = vec![];
             for object in objects {
                 r.issues.push(ObjectWithIssues {
                     metadata: object.clone(),
                     issues: vec![],
                 });
             }
-            issues
         }
         Err(e) => {
             eprintln!(
@@ -147,7 +195,7 @@ pub async fn list_issues_by_category(
     };
 
     match db
-        .get_issues_with_category_for_namespace(category, &namespace_name)
+        .get_issues_with_category_for_namespace(category, namespace_name)
         .await
     {
         Ok(issues) => {
@@ -169,7 +217,7 @@ pub async fn list_issues_by_category(
         }
     };
 
-    Ok(Json(r))
+    Ok(r)
 }
 
 #[utoipa::path(
@@ -187,6 +235,7 @@ pub async fn store_issues(
 ) -> (StatusCode, &'static str) {
     // TODO: check rights, it require another authent than the kube one
 
+	let mut issue_ids = vec![];
 	for issue in issue_list.issues {
 		let id = match db.record_namespaced_object(&issue.object_type, &issue.object_name, &issue.cluster, &issue.namespace).await {
 			Ok(id) => {
@@ -198,6 +247,17 @@ pub async fn store_issues(
 			}
 		};
 
+		let linked_object_id = match issue.linked_object {
+			Some(linked) => match db.record_namespaced_object(&linked.object_type, &linked.object_name, &issue.cluster, &linked.namespace).await {
+				Ok(id) => id.to_string(),
+				Err(e) => {
+					eprintln!("Unable to run db.record_namespaced_object : {}", e);
+					return (StatusCode::INTERNAL_SERVER_ERROR, "Unable to run db.record_namespaced_object");
+				}
+			},
+			None => issue.linked_object_id.unwrap_or("".to_string()),
+		};
+
 		let issue = Issue {
 			object_id: id,
 			category: issue.category,
@@ -208,11 +268,11 @@ pub async fn store_issues(
 			reported_by: issue.reported_by.unwrap_or("".to_string()),
 			reported_at: issue.reported_at.unwrap_or("".to_string()),
 			last_seen_at: issue.last_seen_at.unwrap_or("".to_string()),
-			linked_object_id: issue.linked_object_id.unwrap_or("".to_string()),
+			linked_object_id,
 		};
 
 		match db.add_object_issue(issue).await {
-			Ok(_) => {}
+			Ok(id) => issue_ids.push(id),
 			Err(e) => {
 				eprintln!("Unable to run add_object_issue.add_issue : {}", e);
 				return (StatusCode::INTERNAL_SERVER_ERROR, "Unable to run db.add_issue");
@@ -220,5 +280,12 @@ pub async fn store_issues(
 		}
 	}
 
+	if let Some(resolution) = issue_list.resolution {
+		if let Err(e) = db.resolve_issues(&resolution.cluster, &resolution.reporters, &issue_ids, &[]).await {
+			eprintln!("Unable to run db.resolve_issues : {}", e);
+			return (StatusCode::INTERNAL_SERVER_ERROR, "Unable to run db.resolve_issues");
+		}
+	}
+
     (StatusCode::OK, "{}")
 }
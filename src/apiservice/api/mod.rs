@@ -1,5 +1,6 @@
 pub mod applications;
 pub mod billing;
+pub mod budgets;
 pub mod cluster;
 pub mod compute;
 mod helpers;
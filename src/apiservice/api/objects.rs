@@ -11,3 +11,11 @@ pub struct NamespacedObject {
     pub cluster: String,
 }
 
+
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ObjectReference {
+    #[schema(example = "Deployment")]
+    pub object_type: String,
+    pub object_name: String,
+    pub namespace: String,
+}
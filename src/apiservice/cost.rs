@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+
+use coi::quantity::GIB;
+use coi::resources::{self, ResourceAmounts};
+use coi::workloads::{self, WorkloadRef};
+use k8s_openapi::api::apps::v1::ReplicaSet;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::chrono::{Datelike, NaiveDate, Utc};
+use kube::api::ListParams;
+use log::warn;
+use uuid::Uuid;
+
+use crate::api::billing::{IdleCostReport, WorkloadIdleCost, HOURS_PER_MONTH};
+use crate::api::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::db::Database;
+use crate::usage::PodUsage;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+pub const PRICING_CPU: &str = "cpu";
+pub const PRICING_MEMORY: &str = "memory";
+
+const IDLE_ISSUE_TECH_ID: &str = "idle-reserved-capacity";
+pub const IDLE_ISSUE_REPORTER: &str = "apiservice/idle-cost";
+
+#[derive(Clone, Copy, Debug)]
+pub struct IdleCostSettings {
+    // Deployments using less than this ratio of their requests are considered over-provisioned
+    pub min_usage_ratio: f64,
+}
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct ResourcePricing {
+    pub cpu_core_hour: f64,
+    pub memory_gib_hour: f64,
+}
+
+impl ResourcePricing {
+    pub fn hourly_cost(&self, amounts: &ResourceAmounts) -> f64 {
+        amounts.cpu_cores * self.cpu_core_hour + amounts.memory_bytes / GIB * self.memory_gib_hour
+    }
+}
+
+pub async fn load_resource_pricing(db: &Database) -> Result<ResourcePricing, Error> {
+    let mut pricing = ResourcePricing::default();
+
+    match db.get_pricing_for_object_type(PRICING_CPU).await? {
+        Some(p) => pricing.cpu_core_hour = p.hourly_price(),
+        None => warn!("No pricing defined for '{}', assuming free", PRICING_CPU),
+    }
+    match db.get_pricing_for_object_type(PRICING_MEMORY).await? {
+        Some(p) => pricing.memory_gib_hour = p.hourly_price(),
+        None => warn!("No pricing defined for '{}', assuming free", PRICING_MEMORY),
+    }
+
+    Ok(pricing)
+}
+
+// Compare requests with usage of every running pod of the namespace, aggregated per workload
+pub async fn estimate_idle_cost(
+    kube_client: &kube::Client,
+    namespace: &str,
+    pod_usage: &PodUsage,
+    pricing: &ResourcePricing,
+    usage_source: &str,
+) -> Result<IdleCostReport, kube::Error> {
+    let pods: kube::Api<Pod> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let replicasets: kube::Api<ReplicaSet> = kube::Api::namespaced(kube_client.clone(), namespace);
+    let jobs: kube::Api<Job> = kube::Api::namespaced(kube_client.clone(), namespace);
+
+    let pods = pods.list(&ListParams::default()).await?.items;
+    let replicasets = replicasets.list(&ListParams::default()).await?.items;
+    let jobs = jobs.list(&ListParams::default()).await?.items;
+
+    let mut workloads: BTreeMap<WorkloadRef, WorkloadIdleCost> = BTreeMap::new();
+    for pod in pods.iter() {
+        let used = match pod.metadata.name.as_ref().and_then(|n| pod_usage.get(n)) {
+            Some(u) => u,
+            None => continue,
+        };
+        let requested = match pod.spec.as_ref() {
+            Some(spec) => resources::pod_requests(spec),
+            None => continue,
+        };
+
+        let workload = workloads::owning_workload(pod, &replicasets, &jobs);
+        let entry = workloads
+            .entry(workload.clone())
+            .or_insert_with(|| WorkloadIdleCost {
+                workload_type: workload.kind,
+                workload_name: workload.name,
+                pods: 0,
+                cpu_requested_cores: 0.0,
+                cpu_used_cores: 0.0,
+                memory_requested_bytes: 0.0,
+                memory_used_bytes: 0.0,
+                idle_cpu_cost_per_hour: 0.0,
+                idle_memory_cost_per_hour: 0.0,
+                idle_cost_per_hour: 0.0,
+                idle_cost_per_month: 0.0,
+            });
+
+        entry.pods += 1;
+        entry.cpu_requested_cores += requested.cpu_cores;
+        entry.cpu_used_cores += used.cpu_cores;
+        entry.memory_requested_bytes += requested.memory_bytes;
+        entry.memory_used_bytes += used.memory_bytes;
+
+        // Usage above requests is not idle capacity, and must not offset other pods
+        let idle_cpu = (requested.cpu_cores - used.cpu_cores).max(0.0);
+        let idle_memory = (requested.memory_bytes - used.memory_bytes).max(0.0);
+        entry.idle_cpu_cost_per_hour += idle_cpu * pricing.cpu_core_hour;
+        entry.idle_memory_cost_per_hour += idle_memory / GIB * pricing.memory_gib_hour;
+    }
+
+    let mut report = IdleCostReport {
+        namespace: namespace.to_string(),
+        usage_source: usage_source.to_string(),
+        idle_cost_per_hour: 0.0,
+        idle_cost_per_month: 0.0,
+        workloads: vec![],
+    };
+
+    for (_, mut workload) in workloads {
+        workload.idle_cost_per_hour =
+            workload.idle_cpu_cost_per_hour + workload.idle_memory_cost_per_hour;
+        workload.idle_cost_per_month = workload.idle_cost_per_hour * HOURS_PER_MONTH;
+        report.idle_cost_per_hour += workload.idle_cost_per_hour;
+        report.workloads.push(workload);
+    }
+    report.idle_cost_per_month = report.idle_cost_per_hour * HOURS_PER_MONTH;
+
+    Ok(report)
+}
+
+// Elapsed ratio of the current UTC month, between 0 and 1, the month budget spending is bucketed by
+pub fn month_progress() -> f64 {
+    let now = Utc::now().naive_utc();
+    let month_start = NaiveDate::from_ymd_opt(now.year(), now.month(), 1).unwrap();
+    let next_month_start = if now.month() == 12 {
+        NaiveDate::from_ymd_opt(now.year() + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(now.year(), now.month() + 1, 1).unwrap()
+    };
+
+    let elapsed = now - month_start.and_hms_opt(0, 0, 0).unwrap();
+    let month_length = next_month_start - month_start;
+    elapsed.num_seconds() as f64 / month_length.num_seconds() as f64
+}
+
+pub fn forecast(month_to_date: f64, month_progress: f64) -> f64 {
+    if month_progress > 0.0 {
+        month_to_date / month_progress
+    } else {
+        month_to_date
+    }
+}
+
+fn usage_ratio(used: f64, requested: f64) -> Option<f64> {
+    if requested > 0.0 {
+        Some(used / requested)
+    } else {
+        None
+    }
+}
+
+// Report over-provisioned Deployments of an idle cost report as performance issues, returning the issues reported
+pub async fn record_idle_cost_issues(
+    db: &Database,
+    cluster_name: &str,
+    report: &IdleCostReport,
+    settings: &IdleCostSettings,
+) -> Result<Vec<Uuid>, Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut issue_ids = vec![];
+
+    for workload in report.workloads.iter() {
+        if workload.workload_type != "Deployment" || workload.idle_cost_per_hour <= 0.0 {
+            continue;
+        }
+
+        let cpu_ratio = usage_ratio(workload.cpu_used_cores, workload.cpu_requested_cores);
+        let memory_ratio = usage_ratio(workload.memory_used_bytes, workload.memory_requested_bytes);
+        let lowest_ratio = match (cpu_ratio, memory_ratio) {
+            (Some(c), Some(m)) => c.min(m),
+            (Some(r), None) | (None, Some(r)) => r,
+            (None, None) => continue,
+        };
+        if lowest_ratio >= settings.min_usage_ratio {
+            continue;
+        }
+
+        let severity = if lowest_ratio < settings.min_usage_ratio / 2.0 {
+            IssueSeverity::Medium
+        } else {
+            IssueSeverity::Low
+        };
+
+        let object_id = db
+            .record_namespaced_object(
+                &workload.workload_type,
+                &workload.workload_name,
+                cluster_name,
+                &report.namespace,
+            )
+            .await?;
+
+        let issue_id = db
+            .add_object_issue(Issue {
+                object_id,
+                category: IssueCategory::Performance,
+                details: serde_json::to_string(workload)?,
+                severity,
+                issue_tech_id: IDLE_ISSUE_TECH_ID.to_string(),
+                issue_message: format!(
+                    "Deployment uses {:.0}% of its reserved capacity, wasting {:.2} per month",
+                    lowest_ratio * 100.0,
+                    workload.idle_cost_per_month
+                ),
+                reported_by: IDLE_ISSUE_REPORTER.to_string(),
+                reported_at: now.clone(),
+                last_seen_at: now.clone(),
+                linked_object_id: "".to_string(),
+            })
+            .await?;
+        issue_ids.push(issue_id);
+    }
+
+    Ok(issue_ids)
+}
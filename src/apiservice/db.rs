@@ -1,33 +1,68 @@
 use crate::api::{
+    billing, budgets,
     issues::{self, IssueCategory},
     objects,
 };
 use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
 use log::info;
 use uuid::Uuid;
-use std::{option::Option, result::Result};
+use std::{collections::HashMap, option::Option, result::Result};
 use tokio_postgres::NoTls;
 
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 const STMT_GET_PRICING: &str = "SELECT price, period, description FROM pricing WHERE id = $1";
 const STMT_GET_PRICING_ID_BY_OBJECT_TYPE: &str = "SELECT id FROM pricing WHERE object_type = $1";
+const STMT_GET_NODE_PRICINGS: &str = "SELECT instance_type, price, period, description FROM node_pricing";
+#[allow(dead_code)]
 const STMT_ADD_INVOICE: &str = "INSERT INTO invoice(object_type, object_name, start_time, end_time, price_id VALUES ($1, $2, $3, $4, $5)";
+#[allow(dead_code)]
 const STMT_GET_INVOICE_ID_BY_END_TIME: &str =
     "SELECT id FROM invoice WHERE object_type = $1 AND object_name = $2 AND end_time >= $3";
 //const STMT_GET_OBJECT_ID: &str = "SELECT id FROM namespaced_objects WHERE object_type = $1 AND object_name = $2 AND cluster_name = $3 AND namespace = $4";
 const STMT_CREATE_OBJECT_AND_RETURN_ID: &str = "INSERT INTO namespaced_objects (cluster_name, namespace_name, object_name, object_type) \
 	VALUES ($1,$2,$3,$4) ON CONFLICT ON CONSTRAINT pkey_issues_objects DO UPDATE set cluster_name=$1 RETURNING id";
-const STMT_ADD_OBJECT_ISSUE: &str = "INSERT INTO issues(object_id, category, details, severity, issue_tech_id, issue_message, reported_by, reported_at, last_seen_at, \
-	linked_object_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)";
-const STMT_GET_NAMESPACED_OBJECTS_WITH_ISSUES_WITH_CATEGORY: &str = "SELECT id, object_type, object_name, namespace_name, cluster_name FROM namespaced_objects WHERE \
+// Issues are identified by their object, technical id and linked object: a known issue is refreshed instead of duplicated
+const STMT_ADD_OBJECT_ISSUE: &str = "WITH refreshed AS (\
+		UPDATE issues SET category = $2, details = $3, severity = $4, issue_message = $6, reported_by = $7, \
+		last_seen_at = COALESCE(NULLIF($9::text, '')::timestamp, NOW()) \
+		WHERE object_id = $1 AND issue_tech_id = $5 AND linked_object_id IS NOT DISTINCT FROM NULLIF($10::text, '')::uuid RETURNING id\
+	), \
+	inserted AS (\
+		INSERT INTO issues(object_id, category, details, severity, issue_tech_id, issue_message, reported_by, reported_at, last_seen_at, linked_object_id) \
+		SELECT $1, $2, $3, $4, $5, $6, $7, COALESCE(NULLIF($8::text, '')::timestamp, NOW()), COALESCE(NULLIF($9::text, '')::timestamp, NOW()), \
+		NULLIF($10::text, '')::uuid WHERE NOT EXISTS (SELECT 1 FROM refreshed) RETURNING id\
+	) \
+	SELECT id FROM refreshed UNION ALL SELECT id FROM inserted";
+// Issues of a reporter missing from its last report are resolved
+// Issues of the skipped namespaces are kept, their reporter could not check them
+const STMT_RESOLVE_ISSUES: &str = "DELETE FROM issues WHERE reported_by = ANY($2) AND id <> ALL($3) \
+	AND object_id IN (SELECT id FROM namespaced_objects WHERE cluster_name = $1 AND namespace_name <> ALL($4))";
+const STMT_GET_NAMESPACED_OBJECTS_WITH_ISSUES_WITH_CATEGORY: &str = "SELECT id, object_type, object_name, namespace_name AS namespace, cluster_name AS cluster FROM namespaced_objects WHERE \
 	(\
 		id IN (SELECT object_id FROM issues WHERE category = $2) \
 		OR \
 		id IN (SELECT linked_object_id FROM issues WHERE category = $2) \
 	) AND namespace_name = $1";
-const STMT_GET_ISSUES_WITH_CATEGORY_FOR_NAMESPACE: &str = "SELECT object_id, category, details, severity, issue_tech_id, issue_message, reported_by, reported_at, last_seen_at, linked_object_id \
+const STMT_GET_ISSUES_WITH_CATEGORY_FOR_NAMESPACE: &str = "SELECT object_id, category, details, severity, issue_tech_id, issue_message, reported_by, reported_at::text, last_seen_at::text, \
+	COALESCE(linked_object_id::text, '') AS linked_object_id \
 	FROM issues WHERE object_id IN (SELECT id FROM namespaced_objects WHERE namespace_name = $2) AND category = $1";
+const STMT_UPSERT_BUDGET: &str = "INSERT INTO budgets(name, scope, namespace_name, label_key, label_value, monthly_amount, thresholds) \
+	VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (name) DO UPDATE SET scope = $2, namespace_name = $3, label_key = $4, label_value = $5, \
+	monthly_amount = $6, thresholds = $7";
+const STMT_DELETE_BUDGET: &str = "DELETE FROM budgets WHERE name = $1";
+// Months are UTC months, as the month progress forecasts are computed with
+const STMT_GET_BUDGETS_WITH_SPENDING: &str = "SELECT b.id, b.name, b.scope, b.namespace_name, b.label_key, b.label_value, b.monthly_amount, b.thresholds, \
+	COALESCE(s.amount, 0) AS month_to_date FROM budgets b \
+	LEFT JOIN budget_spendings s ON s.budget_id = b.id AND s.month = date_trunc('month', NOW() AT TIME ZONE 'UTC')::date";
+// Spending accrues from the last accrual time, split by month; the row lock keeps replicas from accruing the same period
+const STMT_ACCRUE_BUDGET_SPENDING: &str = "WITH previous AS (SELECT id, last_accrued_at FROM budgets WHERE id = $1 FOR UPDATE), \
+	accrued AS (UPDATE budgets b SET last_accrued_at = clock_timestamp() FROM previous WHERE b.id = previous.id \
+	RETURNING previous.last_accrued_at AT TIME ZONE 'UTC' AS since, b.last_accrued_at AT TIME ZONE 'UTC' AS until) \
+	INSERT INTO budget_spendings(budget_id, month, amount) \
+	SELECT $1, m::date, $2 * EXTRACT(EPOCH FROM LEAST(m + interval '1 month', a.until) - GREATEST(m, a.since)) / 3600 \
+	FROM accrued a, generate_series(date_trunc('month', a.since), a.until, interval '1 month') m \
+	ON CONFLICT ON CONSTRAINT pkey_budget_spendings DO UPDATE SET amount = budget_spendings.amount + EXCLUDED.amount";
 
 #[derive(Clone)]
 pub struct Database {
@@ -67,24 +102,15 @@ impl Database {
                 cfg.host("localhost");
             }
         };
-        match username {
-            Some(u) => {
-                cfg.user(u.as_str());
-            }
-            None => {}
-        };
-        match password {
-            Some(p) => {
-                cfg.password(p.as_str());
-            }
-            None => {}
-        };
-        match db_name {
-            Some(db) => {
-                cfg.dbname(db.as_str());
-            }
-            None => {}
-        };
+        if let Some(u) = username {
+            cfg.user(u.as_str());
+        }
+        if let Some(p) = password {
+            cfg.password(p.as_str());
+        }
+        if let Some(db) = db_name {
+            cfg.dbname(db.as_str());
+        }
         cfg
     }
 
@@ -156,25 +182,88 @@ impl Database {
         Ok(id)
 	}
 
-    pub async fn add_object_issue(&self, object_issue: issues::Issue) -> Result<(), Error> {
+    pub async fn get_pricing_for_object_type(
+        &self,
+        object_type: &str,
+    ) -> Result<Option<billing::Pricing>, Error> {
         let conn = self.pool.get().await?;
-        conn.execute(
-            STMT_ADD_OBJECT_ISSUE,
-            &[
-                &object_issue.object_id,
-                &object_issue.category,
-                &object_issue.details,
-                &object_issue.severity,
-                &object_issue.issue_tech_id,
-                &object_issue.issue_message,
-                &object_issue.reported_by,
-                &object_issue.reported_at,
-                &object_issue.last_seen_at,
-                &object_issue.linked_object_id,
-            ],
-        )
-        .await?;
-        Ok(())
+        let pricing_id: Uuid = match conn
+            .query_opt(STMT_GET_PRICING_ID_BY_OBJECT_TYPE, &[&object_type])
+            .await?
+        {
+            Some(row) => row.get("id"),
+            None => return Ok(None),
+        };
+
+        let row = conn.query_one(STMT_GET_PRICING, &[&pricing_id]).await?;
+        Ok(Some(billing::Pricing {
+            price: row.get("price"),
+            period: row.get("period"),
+            description: row.get("description"),
+        }))
+    }
+
+    // Node prices indexed by instance type
+    pub async fn get_node_pricings(&self) -> Result<HashMap<String, billing::Pricing>, Error> {
+        let conn = self.pool.get().await?;
+        let rows = conn.query(STMT_GET_NODE_PRICINGS, &[]).await?;
+        let mut pricings = HashMap::new();
+        for row in rows {
+            pricings.insert(
+                row.get("instance_type"),
+                billing::Pricing {
+                    price: row.get("price"),
+                    period: row.get("period"),
+                    description: row.get("description"),
+                },
+            );
+        }
+        Ok(pricings)
+    }
+
+    pub async fn add_object_issue(&self, object_issue: issues::Issue) -> Result<Uuid, Error> {
+        let conn = self.pool.get().await?;
+        let row = conn
+            .query_one(
+                STMT_ADD_OBJECT_ISSUE,
+                &[
+                    &object_issue.object_id,
+                    &object_issue.category,
+                    &object_issue.details,
+                    &object_issue.severity,
+                    &object_issue.issue_tech_id,
+                    &object_issue.issue_message,
+                    &object_issue.reported_by,
+                    &object_issue.reported_at,
+                    &object_issue.last_seen_at,
+                    &object_issue.linked_object_id,
+                ],
+            )
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    // Remove the issues of the reporters on the cluster, except the ones to keep
+    pub async fn resolve_issues(
+        &self,
+        cluster_name: &str,
+        reporters: &[String],
+        kept_issue_ids: &[Uuid],
+        skipped_namespaces: &[String],
+    ) -> Result<u64, Error> {
+        let conn = self.pool.get().await?;
+        let resolved = conn
+            .execute(
+                STMT_RESOLVE_ISSUES,
+                &[
+                    &cluster_name,
+                    &reporters,
+                    &kept_issue_ids,
+                    &skipped_namespaces,
+                ],
+            )
+            .await?;
+        Ok(resolved)
     }
 
     pub async fn get_issues_with_category_for_namespace(
@@ -208,6 +297,64 @@ impl Database {
         Ok(issues)
     }
 
+    pub async fn store_budget(&self, budget: &budgets::Budget) -> Result<(), Error> {
+        let conn = self.pool.get().await?;
+        conn.execute(
+            STMT_UPSERT_BUDGET,
+            &[
+                &budget.name,
+                &budget.scope,
+                &budget.namespace,
+                &budget.label_key,
+                &budget.label_value,
+                &budget.monthly_amount,
+                &budget.thresholds,
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_budget(&self, name: &str) -> Result<bool, Error> {
+        let conn = self.pool.get().await?;
+        let deleted = conn.execute(STMT_DELETE_BUDGET, &[&name]).await?;
+        Ok(deleted > 0)
+    }
+
+    // Budgets with their cost for the current month
+    pub async fn get_budgets_with_spending(
+        &self,
+    ) -> Result<Vec<(Uuid, budgets::Budget, f64)>, Error> {
+        let conn = self.pool.get().await?;
+        let rows = conn.query(STMT_GET_BUDGETS_WITH_SPENDING, &[]).await?;
+        let mut result = Vec::new();
+        for row in rows {
+            let budget = budgets::Budget {
+                name: row.get("name"),
+                scope: row.get("scope"),
+                namespace: row.get("namespace_name"),
+                label_key: row.get("label_key"),
+                label_value: row.get("label_value"),
+                monthly_amount: row.get("monthly_amount"),
+                thresholds: row.get("thresholds"),
+            };
+            result.push((row.get("id"), budget, row.get("month_to_date")));
+        }
+        Ok(result)
+    }
+
+    // Accrue the spending of a budget since its last accrual, at the given hourly cost
+    pub async fn accrue_budget_spending(
+        &self,
+        budget_id: &Uuid,
+        hourly_cost: f64,
+    ) -> Result<(), Error> {
+        let conn = self.pool.get().await?;
+        conn.execute(STMT_ACCRUE_BUDGET_SPENDING, &[budget_id, &hourly_cost])
+            .await?;
+        Ok(())
+    }
+
     pub async fn get_objects_with_issue_category_in_namespace(
         &self,
         category: IssueCategory,
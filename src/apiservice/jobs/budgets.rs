@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::chrono::Utc;
+use log::{error, info};
+use uuid::Uuid;
+
+use crate::allocation::{self, AllocationSettings};
+use crate::api::budgets::{Budget, BudgetScope};
+use crate::api::cluster::ClusterIdentity;
+use crate::api::issues::{Issue, IssueCategory, IssueSeverity};
+use crate::cost;
+use crate::db::Database;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+const THRESHOLD_ISSUE_TECH_ID: &str = "budget-threshold-reached";
+const FORECAST_ISSUE_TECH_ID: &str = "budget-forecast-overrun";
+const BUDGET_ISSUE_REPORTER: &str = "apiservice/budgets";
+
+// Forecasts are too unstable during the first day of the month
+const FORECAST_MIN_MONTH_PROGRESS: f64 = 0.03;
+
+// Periodically accrue the allocated cost of running pods on budgets, and report budgets reaching their thresholds
+pub async fn run(
+    db: Database,
+    kube_client: kube::Client,
+    cluster_identity: ClusterIdentity,
+    settings: AllocationSettings,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        info!("Running budget checks");
+
+        if let Err(e) = check_budgets(&db, &kube_client, cluster_identity.name(), &settings).await {
+            error!("Unable to check budgets: {}", e);
+        }
+    }
+}
+
+fn budget_matches(budget: &Budget, pod: &Pod) -> bool {
+    match budget.scope {
+        BudgetScope::Namespace => pod.metadata.namespace == budget.namespace,
+        BudgetScope::Label => {
+            let (key, value) = match (&budget.label_key, &budget.label_value) {
+                (Some(k), Some(v)) => (k, v),
+                _ => return false,
+            };
+            pod.metadata
+                .labels
+                .as_ref()
+                .and_then(|l| l.get(key))
+                .map(|v| v == value)
+                .unwrap_or(false)
+        }
+    }
+}
+
+// Highest threshold reached by amount, if any
+fn reached_threshold(budget: &Budget, amount: f64) -> Option<f64> {
+    budget
+        .thresholds
+        .iter()
+        .copied()
+        .filter(|t| amount >= t * budget.monthly_amount)
+        .fold(None, |highest: Option<f64>, t| {
+            Some(highest.map_or(t, |h| h.max(t)))
+        })
+}
+
+// Issues of budgets back under their thresholds, deleted or from a previous month are not reported again
+async fn resolve_unreported(
+    db: &Database,
+    cluster_name: &str,
+    issue_ids: &[Uuid],
+) -> Result<(), Error> {
+    db.resolve_issues(
+        cluster_name,
+        &[BUDGET_ISSUE_REPORTER.to_string()],
+        issue_ids,
+        &[],
+    )
+    .await?;
+    Ok(())
+}
+
+async fn check_budgets(
+    db: &Database,
+    kube_client: &kube::Client,
+    cluster_name: &str,
+    settings: &AllocationSettings,
+) -> Result<(), Error> {
+    let budgets = db.get_budgets_with_spending().await?;
+    if budgets.is_empty() {
+        return resolve_unreported(db, cluster_name, &[]).await;
+    }
+
+    // Pods are priced as in the cost allocation report, so budgets add up with it
+    let node_pricings = db.get_node_pricings().await?;
+    let resource_pricing = cost::load_resource_pricing(db).await?;
+    let pod_costs =
+        allocation::pod_hourly_costs(kube_client, &node_pricings, &resource_pricing, settings)
+            .await?;
+
+    // The accrual time is stored with each budget, downtime and other replicas are accounted for
+    for (budget_id, budget, _) in budgets.iter() {
+        let hourly_cost: f64 = pod_costs
+            .iter()
+            .filter(|(pod, _)| budget_matches(budget, pod))
+            .map(|(_, cost)| cost)
+            .sum();
+        db.accrue_budget_spending(budget_id, hourly_cost).await?;
+    }
+
+    let progress = cost::month_progress();
+    let mut issue_ids = vec![];
+
+    for (_, budget, month_to_date) in db.get_budgets_with_spending().await? {
+        let mut namespaces: BTreeSet<String> = pod_costs
+            .iter()
+            .filter(|(pod, _)| budget_matches(&budget, pod))
+            .filter_map(|(pod, _)| pod.metadata.namespace.clone())
+            .collect();
+
+        let forecast = cost::forecast(month_to_date, progress);
+        let issue = match (
+            reached_threshold(&budget, month_to_date),
+            reached_threshold(&budget, forecast),
+        ) {
+            (Some(t), _) => Some((
+                THRESHOLD_ISSUE_TECH_ID,
+                if t >= 1.0 {
+                    IssueSeverity::High
+                } else {
+                    IssueSeverity::Medium
+                },
+                format!(
+                    "Budget {} reached {:.0}% of its monthly amount: {:.2} spent out of {:.2}",
+                    budget.name,
+                    month_to_date / budget.monthly_amount * 100.0,
+                    month_to_date,
+                    budget.monthly_amount
+                ),
+            )),
+            (None, Some(t)) if progress >= FORECAST_MIN_MONTH_PROGRESS => Some((
+                FORECAST_ISSUE_TECH_ID,
+                if t >= 1.0 {
+                    IssueSeverity::Medium
+                } else {
+                    IssueSeverity::Low
+                },
+                format!(
+                    "Budget {} is forecast to reach {:.0}% of its monthly amount: {:.2} out of {:.2}",
+                    budget.name,
+                    forecast / budget.monthly_amount * 100.0,
+                    forecast,
+                    budget.monthly_amount
+                ),
+            )),
+            _ => None,
+        };
+
+        let (issue_tech_id, severity, issue_message) = match issue {
+            Some(i) => i,
+            None => continue,
+        };
+
+        // Namespace budgets are reported on their namespace, recorded under itself as the analyzer files namespaces,
+        // label budgets on every namespace they span
+        let (object_type, object_name) = match budget.scope {
+            BudgetScope::Namespace => {
+                namespaces = budget.namespace.iter().cloned().collect();
+                ("Namespace", budget.namespace.clone().unwrap_or_default())
+            }
+            BudgetScope::Label => ("Budget", budget.name.clone()),
+        };
+
+        let now = Utc::now().to_rfc3339();
+        for namespace in namespaces.iter() {
+            let object_id = db
+                .record_namespaced_object(object_type, &object_name, cluster_name, namespace)
+                .await?;
+            let issue_id = db
+                .add_object_issue(Issue {
+                    object_id,
+                    category: IssueCategory::Cost,
+                    details: serde_json::json!({
+                        "budget": budget,
+                        "month_to_date": month_to_date,
+                        "forecast": forecast,
+                    })
+                    .to_string(),
+                    severity: severity.clone(),
+                    issue_tech_id: issue_tech_id.to_string(),
+                    issue_message: issue_message.clone(),
+                    reported_by: BUDGET_ISSUE_REPORTER.to_string(),
+                    reported_at: now.clone(),
+                    last_seen_at: now.clone(),
+                    linked_object_id: "".to_string(),
+                })
+                .await?;
+            issue_ids.push(issue_id);
+        }
+    }
+
+    resolve_unreported(db, cluster_name, &issue_ids).await
+}
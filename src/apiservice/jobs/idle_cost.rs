@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::ListParams;
+use log::{error, info, warn};
+
+use crate::api::cluster::ClusterIdentity;
+use crate::cost::{self, IdleCostSettings};
+use crate::db::Database;
+use crate::usage;
+
+// Periodically estimate idle cost of every namespace from metrics.k8s.io and report over-provisioned Deployments,
+// resolving the issues of Deployments no longer over-provisioned
+pub async fn run(
+    db: Database,
+    kube_client: kube::Client,
+    cluster_identity: ClusterIdentity,
+    settings: IdleCostSettings,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        info!("Running idle cost estimation");
+
+        let pricing = match cost::load_resource_pricing(&db).await {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Unable to load resource pricing: {}", e);
+                continue;
+            }
+        };
+
+        let namespaces: kube::Api<Namespace> = kube::Api::all(kube_client.clone());
+        let namespaces = match namespaces.list(&ListParams::default()).await {
+            Ok(r) => r.items,
+            Err(e) => {
+                error!("Error while listing namespaces: {}", e);
+                continue;
+            }
+        };
+
+        let mut issue_ids = vec![];
+        // Namespaces whose issues could not be checked keep them
+        let mut skipped_namespaces = vec![];
+        for namespace in namespaces {
+            let namespace_name = match namespace.metadata.name {
+                Some(n) => n,
+                None => continue,
+            };
+
+            let pod_usage = match usage::from_metrics_api(&kube_client, &namespace_name).await {
+                Ok(u) => u,
+                Err(e) => {
+                    warn!(
+                        "Unable to fetch pod metrics for namespace {}: {}",
+                        namespace_name, e
+                    );
+                    skipped_namespaces.push(namespace_name);
+                    continue;
+                }
+            };
+
+            let report = match cost::estimate_idle_cost(
+                &kube_client,
+                &namespace_name,
+                &pod_usage,
+                &pricing,
+                usage::METRICS_API_SOURCE,
+            )
+            .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    error!(
+                        "Unable to estimate idle cost for namespace {}: {}",
+                        namespace_name, e
+                    );
+                    skipped_namespaces.push(namespace_name);
+                    continue;
+                }
+            };
+
+            match cost::record_idle_cost_issues(&db, cluster_identity.name(), &report, &settings)
+                .await
+            {
+                Ok(ids) => issue_ids.extend(ids),
+                Err(e) => {
+                    error!(
+                        "Unable to record idle cost issues for namespace {}: {}",
+                        namespace_name, e
+                    );
+                    skipped_namespaces.push(namespace_name);
+                }
+            }
+        }
+
+        if let Err(e) = db
+            .resolve_issues(
+                cluster_identity.name(),
+                &[cost::IDLE_ISSUE_REPORTER.to_string()],
+                &issue_ids,
+                &skipped_namespaces,
+            )
+            .await
+        {
+            error!("Unable to resolve idle cost issues: {}", e);
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod budgets;
+pub mod idle_cost;
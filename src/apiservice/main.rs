@@ -7,8 +7,12 @@ use utoipa::OpenApi;
 use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
+mod allocation;
 mod api;
+mod cost;
 mod db;
+mod jobs;
+mod usage;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -18,23 +22,53 @@ mod db;
         api::applications::list_gitops_applications,
         api::compute::list,
         api::billing::post_pod_invoice,
+        api::billing::get_idle_cost,
+        api::billing::post_idle_cost,
+        api::billing::get_cost_allocation,
+        api::billing::get_pricing,
+        api::budgets::list_budgets,
+        api::budgets::store_budget,
+        api::budgets::delete_budget,
 		
         api::issues::list_issues_by_category,
+        api::issues::list_cluster_issues_by_category,
         api::issues::store_issues,
     ),
     components(schemas(
         api::objects::NamespacedObject,
+        api::objects::ObjectReference,
+        api::applications::GitOpsApplicationList,
+        coi::gitops::GitOpsTool,
+        coi::gitops::ManagedResource,
+        coi::gitops::GitOpsApplication,
         api::billing::PodBillingEntry,
         api::billing::BillingResult,
+        api::billing::PricingPeriod,
+        api::billing::Pricing,
+        api::billing::WorkloadIdleCost,
+        api::billing::IdleCostReport,
+        api::billing::OverheadAllocation,
+        api::billing::WorkloadAllocation,
+        api::billing::NamespaceAllocation,
+        api::billing::CostAllocationReport,
+        api::budgets::BudgetScope,
+        api::budgets::Budget,
+        api::budgets::BudgetStatus,
+        api::budgets::BudgetList,
         api::cluster::ClusterIdentity,
+        api::compute::ContainerResources,
+        api::compute::ComputeWorkload,
+        api::compute::ComputeList,
 
 		api::issues::ObjectWithIssues,
 		api::issues::IssueCategory,
 		api::issues::IssueSeverity,
         api::issues::IssuesNamespaceParams,
+        api::issues::IssuesClusterParams,
         api::issues::Issue,
         api::issues::PostIssue,
         api::issues::IssueList,
+        api::issues::IssueResolution,
         api::issues::IssueListWithObjects,
     ),)
 )]
@@ -87,6 +121,82 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     info!("request timeout set to {}ms", request_timeout);
 
+    let cost_job_interval = match env::var("COST_JOB_INTERVAL") {
+        Ok(interval) => match interval.parse::<u64>() {
+            Ok(i) if i >= 1 => i,
+            Ok(i) => {
+                eprintln!(
+                    "Invalid COST_JOB_INTERVAL: {}, expecting at least 1 second",
+                    i
+                );
+                3600
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to parse COST_JOB_INTERVAL: {}, not an integer: {}",
+                    interval, e
+                );
+                3600
+            }
+        },
+        Err(_e) => 3600,
+    };
+
+    let budget_job_interval = match env::var("BUDGET_JOB_INTERVAL") {
+        Ok(interval) => match interval.parse::<u64>() {
+            Ok(i) if i >= 1 => i,
+            Ok(i) => {
+                eprintln!(
+                    "Invalid BUDGET_JOB_INTERVAL: {}, expecting at least 1 second",
+                    i
+                );
+                300
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to parse BUDGET_JOB_INTERVAL: {}, not an integer: {}",
+                    interval, e
+                );
+                300
+            }
+        },
+        Err(_e) => 300,
+    };
+
+    let idle_cost_settings = cost::IdleCostSettings {
+        min_usage_ratio: match env::var("IDLE_USAGE_RATIO") {
+            Ok(ratio) => match ratio.parse::<f64>() {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to parse IDLE_USAGE_RATIO: {}, not a number: {}",
+                        ratio, e
+                    );
+                    0.5
+                }
+            },
+            Err(_e) => 0.5,
+        },
+    };
+
+    let allocation_settings = allocation::AllocationSettings {
+        overhead_allocation: match env::var("COST_OVERHEAD_ALLOCATION").as_deref() {
+            Ok("bucket") => api::billing::OverheadAllocation::Bucket,
+            Ok("proportional") | Err(_) => api::billing::OverheadAllocation::Proportional,
+            Ok(mode) => {
+                eprintln!(
+                    "Invalid COST_OVERHEAD_ALLOCATION: {}, expecting proportional or bucket",
+                    mode
+                );
+                api::billing::OverheadAllocation::Proportional
+            }
+        },
+        system_namespaces: match env::var("COST_SYSTEM_NAMESPACES") {
+            Ok(namespaces) => namespaces.split(',').map(|n| n.trim().to_string()).collect(),
+            Err(_e) => vec!["kube-system".to_string()],
+        },
+    };
+
     if env::var("KUBECONFIG").is_err() {
         eprintln!("KUBECONFIG environment variable not set");
         std::process::exit(1);
@@ -94,6 +204,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let db = db::Database::new(db_host, db_name, db_user, db_password, db_pool_size).await?;
     let kube_client = kube::Client::try_default().await.unwrap();
+    let cluster_identity = api::cluster::ClusterIdentity::new(cluster_name);
+
+    tokio::spawn(jobs::idle_cost::run(
+        db.clone(),
+        kube_client.clone(),
+        cluster_identity.clone(),
+        idle_cost_settings,
+        std::time::Duration::from_secs(cost_job_interval),
+    ));
+    tokio::spawn(jobs::budgets::run(
+        db.clone(),
+        kube_client.clone(),
+        cluster_identity.clone(),
+        allocation_settings.clone(),
+        std::time::Duration::from_secs(budget_job_interval),
+    ));
 
     // build our application with a route
     let app: Router<()> = Router::new()
@@ -112,18 +238,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             "/v1/issues/:category/:namespace_name",
             routing::get(api::issues::list_issues_by_category),
         )
+        .route(
+            "/v1/issues/:category",
+            routing::get(api::issues::list_cluster_issues_by_category),
+        )
         .route("/v1/issues", routing::post(api::issues::store_issues))
         .route(
             "/v1/billing/pod",
             routing::post(api::billing::post_pod_invoice),
         )
+        .route(
+            "/v1/billing/idle/:namespace",
+            routing::get(api::billing::get_idle_cost).post(api::billing::post_idle_cost),
+        )
+        .route(
+            "/v1/billing/allocation",
+            routing::get(api::billing::get_cost_allocation),
+        )
+        .route(
+            "/v1/billing/pricing/:object_type",
+            routing::get(api::billing::get_pricing),
+        )
+        .route(
+            "/v1/budgets",
+            routing::get(api::budgets::list_budgets).post(api::budgets::store_budget),
+        )
+        .route(
+            "/v1/budgets/:name",
+            routing::delete(api::budgets::delete_budget),
+        )
         .route("/v1/health/liveness", routing::get(health::liveness))
         .route("/v1/health/readiness", routing::get(health::readiness))
         .layer(
             tower::ServiceBuilder::new()
                 .layer(Extension(db))
                 .layer(Extension(kube_client))
-                .layer(Extension(api::cluster::ClusterIdentity::new(cluster_name)))
+                .layer(Extension(cluster_identity))
+                .layer(Extension(idle_cost_settings))
+                .layer(Extension(allocation_settings))
                 .layer(HandleErrorLayer::new(|_: BoxError| async {
                     error!("request timeout");
                     StatusCode::REQUEST_TIMEOUT
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use coi::quantity;
+use coi::resources::ResourceAmounts;
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind, ListParams};
+
+pub const METRICS_API_SOURCE: &str = "metrics.k8s.io";
+pub const PROMETHEUS_SOURCE: &str = "prometheus";
+
+// CPU usage in cores, as produced by kube-prometheus recording rules. They are alternative
+// computations of the same usage, only the first one exposed is read
+const PROMETHEUS_CPU_METRICS: [&str; 3] = [
+    "node_namespace_pod_container:container_cpu_usage_seconds_total:sum_irate",
+    "node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate",
+    "node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate5m",
+];
+const PROMETHEUS_MEMORY_METRIC: &str = "container_memory_working_set_bytes";
+
+// Usage indexed by pod name
+pub type PodUsage = HashMap<String, ResourceAmounts>;
+
+pub async fn from_metrics_api(
+    kube_client: &kube::Client,
+    namespace: &str,
+) -> Result<PodUsage, kube::Error> {
+    let gvk = GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "PodMetrics");
+    let resource = ApiResource::from_gvk_with_plural(&gvk, "pods");
+    let pod_metrics: kube::Api<DynamicObject> =
+        kube::Api::namespaced_with(kube_client.clone(), namespace, &resource);
+
+    let mut result = PodUsage::new();
+    for pod in pod_metrics.list(&ListParams::default()).await?.items {
+        let pod_name = match pod.metadata.name {
+            Some(n) => n,
+            None => continue,
+        };
+
+        let usage = result.entry(pod_name).or_default();
+        let containers = match pod.data.get("containers").and_then(|c| c.as_array()) {
+            Some(c) => c,
+            None => continue,
+        };
+        for container in containers {
+            let cpu = container["usage"]["cpu"].as_str().and_then(quantity::parse);
+            let memory = container["usage"]["memory"]
+                .as_str()
+                .and_then(quantity::parse);
+            usage.add(&ResourceAmounts {
+                cpu_cores: cpu.unwrap_or(0.0),
+                memory_bytes: memory.unwrap_or(0.0),
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+// Read container usage samples of a namespace from a Prometheus text format scrape
+pub fn from_prometheus_text(scrape: &str, namespace: &str) -> PodUsage {
+    let mut result = PodUsage::new();
+    // CPU samples of the namespace by recording rule, and the preferred rule among them
+    let mut cpu_samples: Vec<(usize, String, f64)> = vec![];
+    let mut cpu_metric = PROMETHEUS_CPU_METRICS.len();
+
+    for line in scrape.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, labels, value) = match parse_sample(line) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let cpu_index = PROMETHEUS_CPU_METRICS.iter().position(|m| *m == name);
+        if cpu_index.is_none() && name != PROMETHEUS_MEMORY_METRIC {
+            continue;
+        }
+
+        if labels.get("namespace").map(String::as_str) != Some(namespace) {
+            continue;
+        }
+
+        // cAdvisor also exposes pod level aggregates, which would be counted twice
+        match labels.get("container").map(String::as_str) {
+            None | Some("") | Some("POD") => continue,
+            Some(_) => {}
+        }
+
+        let pod_name = match labels.get("pod") {
+            Some(p) => p.clone(),
+            None => continue,
+        };
+
+        match cpu_index {
+            // Other namespaces may be covered by other rules, only the ones of the namespace count
+            Some(index) => {
+                cpu_metric = cpu_metric.min(index);
+                cpu_samples.push((index, pod_name, value));
+            }
+            None => result.entry(pod_name).or_default().memory_bytes += value,
+        }
+    }
+
+    for (_, pod_name, value) in cpu_samples.into_iter().filter(|(i, _, _)| *i == cpu_metric) {
+        result.entry(pod_name).or_default().cpu_cores += value;
+    }
+
+    result
+}
+
+fn parse_sample(line: &str) -> Option<(&str, HashMap<String, String>, f64)> {
+    let (name, labels, rest) = match line.find('{') {
+        Some(start) => {
+            let end = start + line[start..].find('}')?;
+            (
+                &line[..start],
+                parse_labels(&line[start + 1..end]),
+                &line[end + 1..],
+            )
+        }
+        None => {
+            let end = line.find(char::is_whitespace)?;
+            (&line[..end], HashMap::new(), &line[end..])
+        }
+    };
+
+    let value = rest.split_whitespace().next()?.parse::<f64>().ok()?;
+    Some((name.trim(), labels, value))
+}
+
+fn parse_labels(raw: &str) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    let mut chars = raw.chars();
+
+    loop {
+        let key: String = chars
+            .by_ref()
+            .skip_while(|c| *c == ',' || c.is_whitespace())
+            .take_while(|c| *c != '=')
+            .collect();
+        if key.is_empty() || chars.next() != Some('"') {
+            break;
+        }
+
+        let mut value = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some(escaped) => value.push(escaped),
+                    None => break,
+                },
+                '"' => break,
+                _ => value.push(c),
+            }
+        }
+
+        labels.insert(key.trim().to_string(), value);
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRAPE: &str = r#"
+# HELP container_memory_working_set_bytes Current working set of the container in bytes
+# TYPE container_memory_working_set_bytes gauge
+container_memory_working_set_bytes{namespace="shop",pod="web-1",container="app"} 104857600 1690000000000
+container_memory_working_set_bytes{namespace="shop",pod="web-1",container="sidecar"} 10485760
+container_memory_working_set_bytes{namespace="shop",pod="web-1",container=""} 115343360
+container_memory_working_set_bytes{namespace="shop",pod="web-1",container="POD"} 1048576
+container_memory_working_set_bytes{namespace="other",pod="db-0",container="db"} 1073741824
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate{namespace="shop",pod="web-1",container="app"} 0.5
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_irate{namespace="shop",pod="web-1",container="app"} 0.25
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_irate{namespace="shop",pod="web-1",container="sidecar"} 0.05
+"#;
+
+    #[test]
+    fn sums_container_memory_of_the_namespace() {
+        let usage = from_prometheus_text(SCRAPE, "shop");
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage["web-1"].memory_bytes, 115343360.0);
+    }
+
+    #[test]
+    fn reads_a_single_cpu_recording_rule() {
+        let usage = from_prometheus_text(SCRAPE, "shop");
+        assert!((usage["web-1"].cpu_cores - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn falls_back_to_the_next_cpu_recording_rule() {
+        let scrape = "node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate5m{namespace=\"shop\",pod=\"web-1\",container=\"app\"} 2";
+        let usage = from_prometheus_text(scrape, "shop");
+        assert_eq!(usage["web-1"].cpu_cores, 2.0);
+    }
+
+    #[test]
+    fn picks_the_cpu_recording_rule_of_the_namespace() {
+        let scrape = r#"
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_irate{namespace="other",pod="db-0",container="db"} 1
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate{namespace="shop",pod="web-1",container="app"} 0.5
+node_namespace_pod_container:container_cpu_usage_seconds_total:sum_rate5m{namespace="shop",pod="web-1",container="app"} 0.75
+"#;
+        let shop = from_prometheus_text(scrape, "shop");
+        assert_eq!(shop["web-1"].cpu_cores, 0.5);
+        let other = from_prometheus_text(scrape, "other");
+        assert_eq!(other["db-0"].cpu_cores, 1.0);
+    }
+
+    #[test]
+    fn parses_escaped_label_values() {
+        let (name, labels, value) =
+            parse_sample(r#"metric{a="x\"y",b="line\nbreak", c="z"} 1.5"#).unwrap();
+        assert_eq!(name, "metric");
+        assert_eq!(labels["a"], "x\"y");
+        assert_eq!(labels["b"], "line\nbreak");
+        assert_eq!(labels["c"], "z");
+        assert_eq!(value, 1.5);
+    }
+
+    #[test]
+    fn parses_samples_without_labels() {
+        let (name, labels, value) = parse_sample("up 1").unwrap();
+        assert_eq!(name, "up");
+        assert!(labels.is_empty());
+        assert_eq!(value, 1.0);
+        assert!(parse_sample("up").is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use kube::api::{DynamicObject, ListParams};
+use kube::discovery::Discovery;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+const ARGOCD_GROUP: &str = "argoproj.io";
+const FLUX_KUSTOMIZE_GROUP: &str = "kustomize.toolkit.fluxcd.io";
+const FLUX_HELM_GROUP: &str = "helm.toolkit.fluxcd.io";
+const FLUX_SOURCE_GROUP: &str = "source.toolkit.fluxcd.io";
+
+const FLUX_SOURCE_KINDS: [&str; 4] = ["GitRepository", "OCIRepository", "Bucket", "HelmRepository"];
+
+pub const HEALTH_HEALTHY: &str = "Healthy";
+pub const HEALTH_DEGRADED: &str = "Degraded";
+pub const HEALTH_PROGRESSING: &str = "Progressing";
+pub const SYNC_SYNCED: &str = "Synced";
+pub const SYNC_OUT_OF_SYNC: &str = "OutOfSync";
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitOpsTool {
+    ArgoCD,
+    Flux,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct ManagedResource {
+    #[schema(example = "Deployment")]
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct GitOpsApplication {
+    pub tool: GitOpsTool,
+    #[schema(example = "Application")]
+    pub kind: String,
+    pub name: String,
+    pub namespace: String,
+    // Namespace the application deploys to, when different from its own
+    pub destination_namespace: Option<String>,
+    pub source_repository: Option<String>,
+    // Path in the repository, or chart name
+    pub source_path: Option<String>,
+    pub target_revision: Option<String>,
+    // Last synced or applied revision
+    pub revision: Option<String>,
+    #[schema(example = "Synced")]
+    pub sync_status: Option<String>,
+    #[schema(example = "Healthy")]
+    pub health_status: Option<String>,
+    pub status_message: Option<String>,
+    // Last transition of the reported status, when the tool tracks it
+    pub status_since: Option<String>,
+    pub resources: Vec<ManagedResource>,
+}
+
+fn string_at(value: &Value, pointer: &str) -> Option<String> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+async fn list_kind(
+    kube_client: &kube::Client,
+    discovery: &Discovery,
+    group: &str,
+    kind: &str,
+) -> Result<Vec<DynamicObject>, kube::Error> {
+    // Tools which are not installed are skipped
+    let resource = match discovery.get(group).and_then(|g| g.recommended_kind(kind)) {
+        Some((resource, _)) => resource,
+        None => return Ok(vec![]),
+    };
+
+    let api: kube::Api<DynamicObject> = kube::Api::all_with(kube_client.clone(), &resource);
+    Ok(api.list(&ListParams::default()).await?.items)
+}
+
+fn argocd_application(app: &DynamicObject) -> GitOpsApplication {
+    let data = &app.data;
+    // Multi sources applications are described by their first source
+    let source = data
+        .pointer("/spec/source")
+        .or_else(|| data.pointer("/spec/sources/0"))
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    let resources = data
+        .pointer("/status/resources")
+        .and_then(Value::as_array)
+        .map(|resources| {
+            resources
+                .iter()
+                .filter_map(|r| {
+                    Some(ManagedResource {
+                        kind: string_at(r, "/kind")?,
+                        namespace: string_at(r, "/namespace"),
+                        name: string_at(r, "/name")?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let status_message = data
+        .pointer("/status/conditions/0/message")
+        .or_else(|| data.pointer("/status/operationState/message"))
+        .and_then(Value::as_str)
+        .map(String::from);
+
+    GitOpsApplication {
+        tool: GitOpsTool::ArgoCD,
+        kind: "Application".to_string(),
+        name: app.metadata.name.clone().unwrap_or_default(),
+        namespace: app.metadata.namespace.clone().unwrap_or_default(),
+        destination_namespace: string_at(data, "/spec/destination/namespace"),
+        source_repository: string_at(&source, "/repoURL"),
+        source_path: string_at(&source, "/path").or_else(|| string_at(&source, "/chart")),
+        target_revision: string_at(&source, "/targetRevision"),
+        revision: string_at(data, "/status/sync/revision"),
+        sync_status: string_at(data, "/status/sync/status"),
+        health_status: string_at(data, "/status/health/status"),
+        status_message,
+        status_since: None,
+        resources,
+    }
+}
+
+// Flux inventory entries ids are formatted as <namespace>_<name>_<group>_<kind>
+fn flux_inventory(data: &Value) -> Vec<ManagedResource> {
+    data.pointer("/status/inventory/entries")
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|e| {
+                    let id = e.get("id")?.as_str()?;
+                    let parts: Vec<&str> = id.split('_').collect();
+                    if parts.len() != 4 {
+                        return None;
+                    }
+                    Some(ManagedResource {
+                        kind: parts[3].to_string(),
+                        namespace: Some(parts[0].to_string()).filter(|n| !n.is_empty()),
+                        name: parts[1].to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn flux_application(
+    object: &DynamicObject,
+    kind: &str,
+    source_ref: &Value,
+    source_path: Option<String>,
+    target_revision: Option<String>,
+    sources: &HashMap<(String, String, String), DynamicObject>,
+) -> GitOpsApplication {
+    let data = &object.data;
+    let namespace = object.metadata.namespace.clone().unwrap_or_default();
+
+    let source = match (
+        string_at(source_ref, "/kind"),
+        string_at(source_ref, "/name"),
+    ) {
+        (Some(kind), Some(name)) => {
+            let source_namespace =
+                string_at(source_ref, "/namespace").unwrap_or_else(|| namespace.clone());
+            sources.get(&(kind, source_namespace, name))
+        }
+        _ => None,
+    };
+    let source_repository = source.and_then(|s| string_at(&s.data, "/spec/url"));
+    // Kustomizations follow the revision referenced by their source
+    let target_revision = target_revision.or_else(|| {
+        let reference = source?.data.pointer("/spec/ref")?.as_object()?;
+        ["commit", "tag", "semver", "branch", "name"]
+            .iter()
+            .find_map(|k| reference.get(*k).and_then(Value::as_str))
+            .map(String::from)
+    });
+
+    let ready = data
+        .pointer("/status/conditions")
+        .and_then(Value::as_array)
+        .and_then(|c| c.iter().find(|c| c["type"] == "Ready"));
+    let health_status = ready.map(|r| match r["status"].as_str() {
+        Some("True") => HEALTH_HEALTHY.to_string(),
+        Some("False") => HEALTH_DEGRADED.to_string(),
+        _ => HEALTH_PROGRESSING.to_string(),
+    });
+
+    let revision = string_at(data, "/status/lastAppliedRevision");
+    let sync_status = string_at(data, "/status/lastAttemptedRevision").map(|attempted| {
+        if Some(&attempted) == revision.as_ref() {
+            SYNC_SYNCED.to_string()
+        } else {
+            SYNC_OUT_OF_SYNC.to_string()
+        }
+    });
+
+    GitOpsApplication {
+        tool: GitOpsTool::Flux,
+        kind: kind.to_string(),
+        name: object.metadata.name.clone().unwrap_or_default(),
+        namespace,
+        destination_namespace: string_at(data, "/spec/targetNamespace"),
+        source_repository,
+        source_path,
+        target_revision,
+        revision,
+        sync_status,
+        health_status,
+        status_message: ready.and_then(|r| string_at(r, "/message")),
+        status_since: ready.and_then(|r| string_at(r, "/lastTransitionTime")),
+        resources: flux_inventory(data),
+    }
+}
+
+// List Argo CD Applications and Flux Kustomizations and HelmReleases, from every installed tool
+pub async fn list_applications(
+    kube_client: &kube::Client,
+) -> Result<Vec<GitOpsApplication>, kube::Error> {
+    let discovery = Discovery::new(kube_client.clone())
+        .filter(&[
+            ARGOCD_GROUP,
+            FLUX_KUSTOMIZE_GROUP,
+            FLUX_HELM_GROUP,
+            FLUX_SOURCE_GROUP,
+        ])
+        .run()
+        .await?;
+
+    let mut sources = HashMap::new();
+    for kind in FLUX_SOURCE_KINDS {
+        for source in list_kind(kube_client, &discovery, FLUX_SOURCE_GROUP, kind).await? {
+            let key = (
+                kind.to_string(),
+                source.metadata.namespace.clone().unwrap_or_default(),
+                source.metadata.name.clone().unwrap_or_default(),
+            );
+            sources.insert(key, source);
+        }
+    }
+
+    let mut applications = vec![];
+    for app in list_kind(kube_client, &discovery, ARGOCD_GROUP, "Application").await? {
+        applications.push(argocd_application(&app));
+    }
+
+    for ks in list_kind(
+        kube_client,
+        &discovery,
+        FLUX_KUSTOMIZE_GROUP,
+        "Kustomization",
+    )
+    .await?
+    {
+        let source_ref = ks.data.pointer("/spec/sourceRef").cloned();
+        applications.push(flux_application(
+            &ks,
+            "Kustomization",
+            &source_ref.unwrap_or(Value::Null),
+            string_at(&ks.data, "/spec/path"),
+            None,
+            &sources,
+        ));
+    }
+
+    for hr in list_kind(kube_client, &discovery, FLUX_HELM_GROUP, "HelmRelease").await? {
+        let chart = hr
+            .data
+            .pointer("/spec/chart/spec")
+            .cloned()
+            .unwrap_or(Value::Null);
+        applications.push(flux_application(
+            &hr,
+            "HelmRelease",
+            chart.get("sourceRef").unwrap_or(&Value::Null),
+            string_at(&chart, "/chart"),
+            string_at(&chart, "/version"),
+            &sources,
+        ));
+    }
+
+    Ok(applications)
+}
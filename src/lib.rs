@@ -0,0 +1,5 @@
+pub mod gitops;
+pub mod pricing;
+pub mod quantity;
+pub mod resources;
+pub mod workloads;
@@ -0,0 +1,24 @@
+use postgres_types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const HOURS_PER_MONTH: f64 = 730.0;
+
+// Period a price is given for, shared by the apiservice and the analyzer reading it
+#[derive(Debug, FromSql, ToSql, Deserialize, Serialize, Clone, Copy, ToSchema)]
+#[postgres(name = "pricing_period", rename_all = "lowercase")]
+pub enum PricingPeriod {
+    Hour,
+    Day,
+    Month,
+}
+
+impl PricingPeriod {
+    pub fn hours(&self) -> f64 {
+        match self {
+            PricingPeriod::Hour => 1.0,
+            PricingPeriod::Day => 24.0,
+            PricingPeriod::Month => HOURS_PER_MONTH,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+const BINARY_SUFFIXES: [(&str, f64); 6] = [
+    ("Ki", 1024.0),
+    ("Mi", 1048576.0),
+    ("Gi", 1073741824.0),
+    ("Ti", 1099511627776.0),
+    ("Pi", 1125899906842624.0),
+    ("Ei", 1152921504606846976.0),
+];
+
+const DECIMAL_SUFFIXES: [(&str, f64); 9] = [
+    ("n", 1e-9),
+    ("u", 1e-6),
+    ("m", 1e-3),
+    ("k", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+    ("E", 1e18),
+];
+
+pub const GIB: f64 = 1073741824.0;
+
+// Parse a Kubernetes quantity string ("100m", "1.5Gi", "2e3") into its base unit value
+pub fn parse(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    for (suffix, multiplier) in BINARY_SUFFIXES.iter() {
+        if let Some(number) = value.strip_suffix(suffix) {
+            return number.parse::<f64>().ok().map(|n| n * multiplier);
+        }
+    }
+
+    for (suffix, multiplier) in DECIMAL_SUFFIXES.iter() {
+        if let Some(number) = value.strip_suffix(suffix) {
+            return number.parse::<f64>().ok().map(|n| n * multiplier);
+        }
+    }
+
+    value.parse::<f64>().ok()
+}
+
+pub fn to_f64(quantity: &Quantity) -> Option<f64> {
+    parse(&quantity.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_decimal_suffixes() {
+        assert_eq!(parse("100m"), Some(0.1));
+        assert_eq!(parse("2k"), Some(2000.0));
+        assert_eq!(parse("1G"), Some(1e9));
+        assert_eq!(parse("250000n"), Some(0.00025));
+    }
+
+    #[test]
+    fn parses_binary_suffixes() {
+        assert_eq!(parse("1Gi"), Some(GIB));
+        assert_eq!(parse("1.5Gi"), Some(1.5 * GIB));
+        assert_eq!(parse("512Mi"), Some(536870912.0));
+    }
+
+    #[test]
+    fn parses_plain_numbers() {
+        assert_eq!(parse("2"), Some(2.0));
+        assert_eq!(parse(" 0.5 "), Some(0.5));
+        assert_eq!(parse("2e3"), Some(2000.0));
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("Gi"), None);
+        assert_eq!(parse("abc"), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{Container, PodSpec};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+use crate::quantity;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct ResourceAmounts {
+    pub cpu_cores: f64,
+    pub memory_bytes: f64,
+}
+
+impl ResourceAmounts {
+    pub fn add(&mut self, other: &ResourceAmounts) {
+        self.cpu_cores += other.cpu_cores;
+        self.memory_bytes += other.memory_bytes;
+    }
+
+    pub fn from_resource_list(list: Option<&BTreeMap<String, Quantity>>) -> Self {
+        let amount = |name: &str| {
+            list.and_then(|l| l.get(name))
+                .and_then(quantity::to_f64)
+                .unwrap_or(0.0)
+        };
+        Self {
+            cpu_cores: amount("cpu"),
+            memory_bytes: amount("memory"),
+        }
+    }
+
+    // Amounts with the resources left unset taken from fallback
+    pub fn or(&self, fallback: &ResourceAmounts) -> Self {
+        let pick = |amount: f64, fallback: f64| if amount > 0.0 { amount } else { fallback };
+        Self {
+            cpu_cores: pick(self.cpu_cores, fallback.cpu_cores),
+            memory_bytes: pick(self.memory_bytes, fallback.memory_bytes),
+        }
+    }
+
+    // Largest amount of each resource
+    pub fn max(&self, other: &ResourceAmounts) -> Self {
+        Self {
+            cpu_cores: self.cpu_cores.max(other.cpu_cores),
+            memory_bytes: self.memory_bytes.max(other.memory_bytes),
+        }
+    }
+}
+
+fn container_requests(container: &Container) -> ResourceAmounts {
+    let requests = container
+        .resources
+        .as_ref()
+        .and_then(|r| r.requests.as_ref());
+    ResourceAmounts::from_resource_list(requests)
+}
+
+// Effective requests of a pod, as the scheduler reserves them: init containers run one at a
+// time before the regular ones, so the largest of them competes with the sum of the others
+pub fn pod_requests(spec: &PodSpec) -> ResourceAmounts {
+    let mut total = ResourceAmounts::default();
+    for container in spec.containers.iter() {
+        total.add(&container_requests(container));
+    }
+    for container in spec.init_containers.iter().flatten() {
+        total = total.max(&container_requests(container));
+    }
+    total.add(&ResourceAmounts::from_resource_list(spec.overhead.as_ref()));
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::ResourceRequirements;
+
+    fn container(cpu: &str, memory: &str) -> Container {
+        Container {
+            resources: Some(ResourceRequirements {
+                requests: Some(BTreeMap::from([
+                    ("cpu".to_string(), Quantity(cpu.to_string())),
+                    ("memory".to_string(), Quantity(memory.to_string())),
+                ])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sums_regular_containers() {
+        let spec = PodSpec {
+            containers: vec![container("100m", "128Mi"), container("200m", "64Mi")],
+            ..Default::default()
+        };
+        let requests = pod_requests(&spec);
+        assert!((requests.cpu_cores - 0.3).abs() < 1e-9);
+        assert_eq!(requests.memory_bytes, 192.0 * 1048576.0);
+    }
+
+    #[test]
+    fn reserves_the_largest_init_container() {
+        let spec = PodSpec {
+            containers: vec![container("100m", "128Mi"), container("200m", "64Mi")],
+            init_containers: Some(vec![container("1", "64Mi"), container("100m", "32Mi")]),
+            ..Default::default()
+        };
+        let requests = pod_requests(&spec);
+        assert_eq!(requests.cpu_cores, 1.0);
+        assert_eq!(requests.memory_bytes, 192.0 * 1048576.0);
+    }
+
+    #[test]
+    fn adds_pod_overhead() {
+        let spec = PodSpec {
+            containers: vec![container("100m", "128Mi")],
+            overhead: Some(BTreeMap::from([
+                ("cpu".to_string(), Quantity("250m".to_string())),
+                ("memory".to_string(), Quantity("120Mi".to_string())),
+            ])),
+            ..Default::default()
+        };
+        let requests = pod_requests(&spec);
+        assert!((requests.cpu_cores - 0.35).abs() < 1e-9);
+        assert_eq!(requests.memory_bytes, 248.0 * 1048576.0);
+    }
+
+    #[test]
+    fn falls_back_on_unset_resources() {
+        let set = ResourceAmounts {
+            cpu_cores: 0.5,
+            memory_bytes: 0.0,
+        };
+        let fallback = ResourceAmounts {
+            cpu_cores: 2.0,
+            memory_bytes: 1024.0,
+        };
+        assert_eq!(
+            set.or(&fallback),
+            ResourceAmounts {
+                cpu_cores: 0.5,
+                memory_bytes: 1024.0,
+            }
+        );
+    }
+}
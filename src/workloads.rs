@@ -0,0 +1,62 @@
+use k8s_openapi::api::apps::v1::ReplicaSet;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WorkloadRef {
+    pub kind: String,
+    pub name: String,
+}
+
+fn controller_of(metadata: &ObjectMeta) -> Option<&OwnerReference> {
+    metadata
+        .owner_references
+        .as_ref()?
+        .iter()
+        .find(|o| o.controller.unwrap_or(false))
+}
+
+// Controller of the owner, when the owner is among the given objects of the pod namespace
+fn owner_controller<'a>(
+    owner: &OwnerReference,
+    pod: &Pod,
+    mut objects: impl Iterator<Item = &'a ObjectMeta>,
+) -> Option<&'a OwnerReference> {
+    objects
+        .find(|m| {
+            m.name.as_deref() == Some(owner.name.as_str()) && m.namespace == pod.metadata.namespace
+        })
+        .and_then(controller_of)
+}
+
+// Resolve the top level controller of a pod, following ReplicaSets up to their Deployment
+// and Jobs up to their CronJob
+pub fn owning_workload(pod: &Pod, replicasets: &[ReplicaSet], jobs: &[Job]) -> WorkloadRef {
+    let owner = match controller_of(&pod.metadata) {
+        Some(o) => o,
+        None => {
+            return WorkloadRef {
+                kind: "Pod".to_string(),
+                name: pod.metadata.name.clone().unwrap_or_default(),
+            }
+        }
+    };
+
+    let parent = match owner.kind.as_str() {
+        "ReplicaSet" => owner_controller(owner, pod, replicasets.iter().map(|r| &r.metadata)),
+        "Job" => owner_controller(owner, pod, jobs.iter().map(|j| &j.metadata)),
+        _ => None,
+    };
+    if let Some(p) = parent {
+        return WorkloadRef {
+            kind: p.kind.clone(),
+            name: p.name.clone(),
+        };
+    }
+
+    WorkloadRef {
+        kind: owner.kind.clone(),
+        name: owner.name.clone(),
+    }
+}